@@ -0,0 +1,366 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use sevenz_rust::lzma::LZMA2Options;
+use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+use crate::archive_names::FilenamePolicy;
+use crate::utils::create_zip_from_entries;
+
+/// Output archive format of a bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    /// 7z with LZMA2; the compression method is ignored, the level selects the LZMA2 preset
+    SevenZ,
+    /// tar stream compressed with Zstandard
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// Parse a format name as sent by the frontend ("zip", "7z", "tar.zst")
+    pub fn parse(name: &str) -> Result<ArchiveFormat, String> {
+        match name.to_lowercase().as_str() {
+            "zip" => Ok(ArchiveFormat::Zip),
+            "7z" => Ok(ArchiveFormat::SevenZ),
+            "tar.zst" | "tzst" => Ok(ArchiveFormat::TarZst),
+            other => Err(format!("Unknown archive format: {}", other)),
+        }
+    }
+
+    /// Format of an existing archive, from its file name
+    pub fn from_file_name(name: &str) -> Option<ArchiveFormat> {
+        let name = name.to_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if name.ends_with(".7z") {
+            Some(ArchiveFormat::SevenZ)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Some(ArchiveFormat::TarZst)
+        } else {
+            None
+        }
+    }
+
+    /// File extension without the leading dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::SevenZ => "7z",
+            ArchiveFormat::TarZst => "tar.zst",
+        }
+    }
+
+    /// Append the format's extension to a file name unless it is already there
+    pub fn with_extension(self, name: &str) -> String {
+        if name.to_lowercase().ends_with(&format!(".{}", self.extension())) {
+            name.to_string()
+        } else {
+            format!("{}.{}", name, self.extension())
+        }
+    }
+
+    /// File name without the format's extension
+    pub fn stem(self, name: &str) -> String {
+        let suffix = format!(".{}", self.extension());
+        if name.to_lowercase().ends_with(&suffix) {
+            name[..name.len() - suffix.len()].to_string()
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+/// Modification time used in reproducible mode when neither the request nor
+/// `SOURCE_DATE_EPOCH` gives one: 1980-01-01 00:00:00 UTC, the earliest time a ZIP can store
+const DEFAULT_SOURCE_DATE_EPOCH: i64 = 315_532_800;
+
+/// Settings shared by all archive writers
+#[derive(Debug, Clone)]
+pub struct ArchiveOptions {
+    pub compression_method: String,
+    pub compression_level: u8,
+    pub filename_policy: FilenamePolicy,
+    /// Reproducible mode: every entry gets this modification time (Unix seconds) and
+    /// normalized permissions, so the same inputs give byte-identical archives
+    pub fixed_mtime: Option<i64>,
+}
+
+/// Modification time for reproducible archives: the requested value,
+/// then the `SOURCE_DATE_EPOCH` environment variable, then 1980-01-01
+pub fn source_date_epoch(requested: Option<i64>) -> i64 {
+    requested
+        .or_else(|| std::env::var("SOURCE_DATE_EPOCH").ok()?.trim().parse().ok())
+        .unwrap_or(DEFAULT_SOURCE_DATE_EPOCH)
+        .max(DEFAULT_SOURCE_DATE_EPOCH)
+}
+
+/// Write `entries` (files and folders below `src_dir`) into an archive of the given format.
+/// Entries are written in the given order, except by the parallel ZIP writer outside reproducible mode.
+/// `progress` is called with (entries done, total entries) while the archive is written.
+pub fn create_archive(
+    format: ArchiveFormat,
+    src_dir: &Path,
+    entries: &[PathBuf],
+    dest_file: &Path,
+    options: &ArchiveOptions,
+    progress: &dyn Fn(usize, usize),
+) -> io::Result<()> {
+    match format {
+        ArchiveFormat::Zip => create_zip_from_entries(src_dir, entries, dest_file, options, progress),
+        ArchiveFormat::SevenZ => create_7z(src_dir, entries, dest_file, options, progress),
+        ArchiveFormat::TarZst => create_tar_zst(src_dir, entries, dest_file, options, progress),
+    }
+}
+
+/// Archive name of an entry, or None for `src_dir` itself
+fn entry_name(src_dir: &Path, path: &Path, filename_policy: FilenamePolicy) -> Option<String> {
+    let name = path.strip_prefix(src_dir).ok()?;
+    if name.as_os_str().is_empty() {
+        return None;
+    }
+    Some(filename_policy.archive_name(&name.to_string_lossy().replace('\\', "/")))
+}
+
+fn create_7z(
+    src_dir: &Path,
+    entries: &[PathBuf],
+    dest_file: &Path,
+    options: &ArchiveOptions,
+    progress: &dyn Fn(usize, usize),
+) -> io::Result<()> {
+    let mut writer = SevenZWriter::create(dest_file).map_err(io::Error::other)?;
+    // 7z always uses LZMA2; the compression level (0-9) selects its preset
+    let preset = u32::from(options.compression_level.min(9));
+    writer.set_content_methods(vec![LZMA2Options::with_preset(preset).into()]);
+
+    for (i, path) in entries.iter().enumerate() {
+        let name = match entry_name(src_dir, path, options.filename_policy) {
+            Some(name) => name,
+            None => continue,
+        };
+        let mut entry = SevenZArchiveEntry::from_path(path, name);
+        if let Some(mtime) = options.fixed_mtime {
+            // Only the fixed modification date, no creation or access dates
+            entry.last_modified_date = (UNIX_EPOCH + Duration::from_secs(mtime as u64))
+                .try_into()
+                .map_err(io::Error::other)?;
+            entry.has_last_modified_date = true;
+            entry.has_creation_date = false;
+            entry.has_access_date = false;
+            entry.has_windows_attributes = false;
+        }
+        if path.is_file() {
+            writer.push_archive_entry(entry, Some(File::open(path)?)).map_err(io::Error::other)?;
+        } else {
+            writer.push_archive_entry::<File>(entry, None).map_err(io::Error::other)?;
+        }
+        progress(i + 1, entries.len());
+    }
+
+    writer.finish()?;
+    log_info!("[7Z] Successfully written {} bytes", fs::metadata(dest_file)?.len());
+    Ok(())
+}
+
+fn create_tar_zst(
+    src_dir: &Path,
+    entries: &[PathBuf],
+    dest_file: &Path,
+    options: &ArchiveOptions,
+    progress: &dyn Fn(usize, usize),
+) -> io::Result<()> {
+    // Zstandard levels go up to 22, but anything above 19 needs a lot of memory
+    let level = (options.compression_level as i32).clamp(1, 19);
+    let mut encoder = zstd::stream::write::Encoder::new(File::create(dest_file)?, level)?;
+    // tar has no checksums for file data, so let zstd add one per frame
+    encoder.include_checksum(true)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    for (i, path) in entries.iter().enumerate() {
+        let name = match entry_name(src_dir, path, options.filename_policy) {
+            Some(name) => name,
+            None => continue,
+        };
+        if let Some(mtime) = options.fixed_mtime {
+            append_normalized(&mut builder, path, &name, mtime)?;
+        } else if path.is_dir() {
+            builder.append_dir(&name, path)?;
+        } else if path.is_file() {
+            builder.append_path_with_name(path, &name)?;
+        }
+        progress(i + 1, entries.len());
+    }
+
+    builder.into_inner()?.finish()?;
+    log_info!("[TAR.ZST] Successfully written {} bytes", fs::metadata(dest_file)?.len());
+    Ok(())
+}
+
+/// Append an entry with a fixed time, root ownership and 0644/0755 permissions
+fn append_normalized<W: io::Write>(builder: &mut tar::Builder<W>, path: &Path, name: &str, mtime: i64) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_mtime(mtime as u64);
+    header.set_uid(0);
+    header.set_gid(0);
+    if path.is_dir() {
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_size(0);
+        builder.append_data(&mut header, name, io::empty())
+    } else {
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_mode(0o644);
+        header.set_size(fs::metadata(path)?.len());
+        builder.append_data(&mut header, name, File::open(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_dir;
+    use std::cell::RefCell;
+    use std::io::Read;
+    use sevenz_rust::{Password, SevenZReader};
+
+    fn options(level: u8) -> ArchiveOptions {
+        ArchiveOptions {
+            compression_method: "deflate".to_string(),
+            compression_level: level,
+            filename_policy: FilenamePolicy::Utf8,
+            fixed_mtime: None,
+        }
+    }
+
+    // Bundle folder with a map and a texture; returns the entries in archive order
+    fn bundle_folder(src: &Path) -> Vec<PathBuf> {
+        fs::create_dir_all(src.join("maps/Grundorf")).unwrap();
+        fs::create_dir_all(src.join("Texture")).unwrap();
+        fs::write(src.join("maps/Grundorf/global.cfg"), "[friendlyname]\nGrundorf\n").unwrap();
+        fs::write(src.join("Texture/road.dds"), "road texture").unwrap();
+        ["maps", "maps/Grundorf", "maps/Grundorf/global.cfg", "Texture", "Texture/road.dds"]
+            .iter()
+            .map(|p| src.join(p))
+            .collect()
+    }
+
+    // Write an archive, returning every progress call
+    fn write(format: ArchiveFormat, src: &Path, entries: &[PathBuf], dest: &Path, options: &ArchiveOptions) -> Vec<(usize, usize)> {
+        let calls = RefCell::new(Vec::new());
+        create_archive(format, src, entries, dest, options, &|done, total| calls.borrow_mut().push((done, total))).unwrap();
+        calls.into_inner()
+    }
+
+    // Entry names with the content of files (None for folders)
+    fn read_7z(path: &Path) -> Vec<(String, Option<String>)> {
+        let mut entries = Vec::new();
+        let mut reader = SevenZReader::open(path, Password::empty()).unwrap();
+        reader.for_each_entries(|entry, data| {
+            let content = (!entry.is_directory()).then(|| {
+                let mut content = String::new();
+                data.read_to_string(&mut content).map(|_| content)
+            });
+            entries.push((entry.name().to_string(), content.transpose()?));
+            Ok(true)
+        }).unwrap();
+        entries
+    }
+
+    fn read_tar_zst(path: &Path) -> Vec<(String, Option<String>)> {
+        let decoder = zstd::stream::read::Decoder::new(File::open(path).unwrap()).unwrap();
+        let mut tar = tar::Archive::new(decoder);
+        tar.entries().unwrap().map(|entry| {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().trim_end_matches('/').to_string();
+            let content = (!entry.header().entry_type().is_dir()).then(|| {
+                let mut content = String::new();
+                entry.read_to_string(&mut content).unwrap();
+                content
+            });
+            (name, content)
+        }).collect()
+    }
+
+    fn expected() -> Vec<(String, Option<String>)> {
+        vec![
+            ("maps".to_string(), None),
+            ("maps/Grundorf".to_string(), None),
+            ("maps/Grundorf/global.cfg".to_string(), Some("[friendlyname]\nGrundorf\n".to_string())),
+            ("Texture".to_string(), None),
+            ("Texture/road.dds".to_string(), Some("road texture".to_string())),
+        ]
+    }
+
+    #[test]
+    fn round_trips_7z() {
+        let dir = test_dir("archive_7z");
+        let src = dir.join("bundle");
+        let entries = bundle_folder(&src);
+        let dest = dir.join("Grundorf.7z");
+
+        let calls = write(ArchiveFormat::SevenZ, &src, &entries, &dest, &options(6));
+        assert_eq!(calls, (1..=5).map(|done| (done, 5)).collect::<Vec<_>>());
+        // The reader lists files with data before empty entries
+        let mut read = read_7z(&dest);
+        read.sort();
+        let mut expected = expected();
+        expected.sort();
+        assert_eq!(read, expected);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn round_trips_tar_zst() {
+        let dir = test_dir("archive_tar_zst");
+        let src = dir.join("bundle");
+        let entries = bundle_folder(&src);
+        let dest = dir.join("Grundorf.tar.zst");
+
+        let calls = write(ArchiveFormat::TarZst, &src, &entries, &dest, &options(6));
+        assert_eq!(calls, (1..=5).map(|done| (done, 5)).collect::<Vec<_>>());
+        assert_eq!(read_tar_zst(&dest), expected());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reports_zip_progress_up_to_the_last_entry() {
+        let dir = test_dir("archive_zip");
+        let src = dir.join("bundle");
+        let entries = bundle_folder(&src);
+
+        let calls = write(ArchiveFormat::Zip, &src, &entries, &dir.join("Grundorf.zip"), &options(6));
+        assert_eq!(calls.last(), Some(&(5, 5)));
+        assert!(calls.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn applies_the_7z_compression_level() {
+        let dir = test_dir("archive_7z_level");
+        let src = dir.join("bundle");
+        fs::create_dir_all(&src).unwrap();
+        // Text with long-range repetitions, which only the larger presets find
+        let mut seed = 1u64;
+        let text: String = (0..20_000).map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            format!("{} ", seed % 5000)
+        }).collect();
+        fs::write(src.join("tile_0_0.map"), text.repeat(3)).unwrap();
+        let entries = vec![src.join("tile_0_0.map")];
+
+        let size = |level: u8| {
+            let dest = dir.join(format!("level{}.7z", level));
+            write(ArchiveFormat::SevenZ, &src, &entries, &dest, &options(level));
+            fs::metadata(dest).unwrap().len()
+        };
+        assert!(size(9) < size(0));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
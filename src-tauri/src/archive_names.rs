@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// How file names that are not plain ASCII are written into the archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilenamePolicy {
+    /// Keep the names and mark them as UTF-8 (general purpose flag bit 11)
+    Utf8,
+    /// Replace diacritics with their ASCII base letters
+    Transliterate,
+    /// Refuse to create the archive
+    Reject,
+}
+
+impl FilenamePolicy {
+    /// Parse a policy name as sent by the frontend ("utf8", "transliterate", "reject")
+    pub fn parse(name: &str) -> Result<FilenamePolicy, String> {
+        match name.to_lowercase().as_str() {
+            "utf8" | "utf-8" => Ok(FilenamePolicy::Utf8),
+            "transliterate" => Ok(FilenamePolicy::Transliterate),
+            "reject" => Ok(FilenamePolicy::Reject),
+            other => Err(format!("Unknown filename policy: {}", other)),
+        }
+    }
+
+    /// Name of an entry as it will be stored in the archive
+    pub fn archive_name(self, name: &str) -> String {
+        match self {
+            FilenamePolicy::Transliterate => transliterate(name),
+            _ => name.to_string(),
+        }
+    }
+}
+
+/// Archive-relative paths (forward slashes) of all files and folders below `dir` whose name is not ASCII
+pub fn non_ascii_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(dir).ok()?.to_string_lossy().replace('\\', "/");
+            (!rel.is_ascii()).then_some(rel)
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// Entries below `dir` that would end up with the same archive name (ignoring case, like Windows)
+/// after applying the policy
+pub fn colliding_names(dir: &Path, policy: FilenamePolicy) -> Vec<String> {
+    let mut seen: HashMap<String, String> = HashMap::new();
+    let mut collisions = Vec::new();
+    for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+        let rel = match entry.path().strip_prefix(dir) {
+            Ok(rel) => rel.to_string_lossy().replace('\\', "/"),
+            Err(_) => continue,
+        };
+        let mapped = policy.archive_name(&rel).to_lowercase();
+        if let Some(other) = seen.insert(mapped, rel.clone()) {
+            collisions.push(format!("{} <-> {}", other, rel));
+        }
+    }
+    collisions
+}
+
+/// Replace Latin letters with diacritics by their ASCII base letters.
+/// Combining marks of decomposed names (as written by macOS) are dropped,
+/// anything else outside ASCII becomes `_`.
+pub fn transliterate(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii() {
+            result.push(c);
+            continue;
+        }
+        let replacement = match c {
+            'á' | 'à' | 'â' | 'ä' | 'ã' | 'å' | 'ă' | 'ą' => "a",
+            'Á' | 'À' | 'Â' | 'Ä' | 'Ã' | 'Å' | 'Ă' | 'Ą' => "A",
+            'č' | 'ć' | 'ç' => "c",
+            'Č' | 'Ć' | 'Ç' => "C",
+            'ď' | 'đ' => "d",
+            'Ď' | 'Đ' => "D",
+            'é' | 'è' | 'ê' | 'ë' | 'ě' | 'ę' => "e",
+            'É' | 'È' | 'Ê' | 'Ë' | 'Ě' | 'Ę' => "E",
+            'í' | 'ì' | 'î' | 'ï' => "i",
+            'Í' | 'Ì' | 'Î' | 'Ï' => "I",
+            'ľ' | 'ĺ' | 'ł' => "l",
+            'Ľ' | 'Ĺ' | 'Ł' => "L",
+            'ň' | 'ń' | 'ñ' => "n",
+            'Ň' | 'Ń' | 'Ñ' => "N",
+            'ó' | 'ò' | 'ô' | 'ö' | 'õ' | 'ő' | 'ø' => "o",
+            'Ó' | 'Ò' | 'Ô' | 'Ö' | 'Õ' | 'Ő' | 'Ø' => "O",
+            'ř' | 'ŕ' => "r",
+            'Ř' | 'Ŕ' => "R",
+            'š' | 'ś' | 'ş' => "s",
+            'Š' | 'Ś' | 'Ş' => "S",
+            'ß' => "ss",
+            'ť' | 'ţ' => "t",
+            'Ť' | 'Ţ' => "T",
+            'ú' | 'ù' | 'û' | 'ü' | 'ů' | 'ű' => "u",
+            'Ú' | 'Ù' | 'Û' | 'Ü' | 'Ů' | 'Ű' => "U",
+            'ý' | 'ÿ' => "y",
+            'Ý' | 'Ÿ' => "Y",
+            'ž' | 'ź' | 'ż' => "z",
+            'Ž' | 'Ź' | 'Ż' => "Z",
+            'æ' => "ae",
+            'Æ' => "AE",
+            'œ' => "oe",
+            'Œ' => "OE",
+            '\u{0300}'..='\u{036F}' => "",
+            _ => "_",
+        };
+        result.push_str(replacement);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::test_utils::{test_dir, write_files};
+
+    #[test]
+    fn parses_policy_names() {
+        assert_eq!(FilenamePolicy::parse("utf8"), Ok(FilenamePolicy::Utf8));
+        assert_eq!(FilenamePolicy::parse("UTF-8"), Ok(FilenamePolicy::Utf8));
+        assert_eq!(FilenamePolicy::parse("Transliterate"), Ok(FilenamePolicy::Transliterate));
+        assert_eq!(FilenamePolicy::parse("reject"), Ok(FilenamePolicy::Reject));
+        assert!(FilenamePolicy::parse("ascii").is_err());
+    }
+
+    #[test]
+    fn transliterates_to_ascii() {
+        assert_eq!(transliterate("Texture/Straße_Café.dds"), "Texture/Strasse_Cafe.dds");
+        assert_eq!(transliterate("Žluťoučký kůň"), "Zlutoucky kun");
+        assert_eq!(transliterate("Œuvre Æther"), "OEuvre AEther");
+        // Decomposed "á" keeps its base letter
+        assert_eq!(transliterate("a\u{0301}b.txt"), "ab.txt");
+        assert_eq!(transliterate("Москва.txt"), "______.txt");
+        assert_eq!(transliterate("plain.txt"), "plain.txt");
+    }
+
+    #[test]
+    fn lists_non_ascii_names() {
+        let dir = test_dir("names_non_ascii");
+        write_files(&dir, &[("Texture/Café.dds", ""), ("Texture/plain.dds", ""), ("Über/a.txt", "")]);
+
+        assert_eq!(non_ascii_names(&dir), vec!["Texture/Café.dds", "Über", "Über/a.txt"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn finds_names_that_collide_after_transliteration() {
+        let dir = test_dir("names_colliding");
+        write_files(&dir, &[("Texture/Café.dds", ""), ("Texture/cafe.dds", ""), ("Texture/Straße.dds", "")]);
+
+        let collisions = colliding_names(&dir, FilenamePolicy::Transliterate);
+        assert_eq!(collisions.len(), 1);
+        assert!(collisions[0].contains("Texture/Café.dds") && collisions[0].contains("Texture/cafe.dds"));
+        assert!(colliding_names(&dir, FilenamePolicy::Utf8).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
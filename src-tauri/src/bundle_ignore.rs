@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use walkdir::WalkDir;
+use crate::file_index::normalize_key;
+
+/// Name of the ignore files looked up in every copied folder and its subfolders
+pub const IGNORE_FILE_NAME: &str = ".bundleignore";
+
+/// Patterns that are always applied before the folder's own `.bundleignore`.
+/// A `.bundleignore` can re-include any of these with a `!pattern` line.
+const DEFAULT_PATTERNS: &[&str] = &[
+    // Editor backups and autosaves
+    "*.bak",
+    "*.backup",
+    "*.tmp",
+    "*~",
+    "backup/",
+    "autosave/",
+    "*.rar",
+    ".bundleignore",
+    // OS metadata
+    "Thumbs.db",
+    "desktop.ini",
+    ".DS_Store",
+];
+
+/// Earlier output of the bundler, excluded whatever the `.bundleignore` files say
+const OUTPUT_PATTERNS: &[&str] = &[
+    "*.zip",
+    "*.7z",
+    "*.tar.zst",
+    "debug_collected_paths.txt",
+];
+
+/// Gitignore-style exclusion rules for one copied folder (map, addon or folder dependency)
+pub struct BundleIgnore {
+    /// Rules of each folder with a `.bundleignore`, relative to the copied folder and lowercased,
+    /// deepest first. The copied folder itself always has rules (the defaults).
+    matchers: Vec<(PathBuf, Gitignore)>,
+    /// Earlier bundler output
+    output: Gitignore,
+}
+
+impl BundleIgnore {
+    /// Build the rules for `folder` from the defaults and the `.bundleignore` files in it.
+    /// As in git, the patterns of a file are relative to its folder, and the file closest
+    /// to a path decides. Patterns are matched case-insensitively, like OMSI itself.
+    pub fn load(folder: &Path) -> BundleIgnore {
+        let mut matchers = vec![(PathBuf::new(), build_matcher(folder, DEFAULT_PATTERNS, Some(&folder.join(IGNORE_FILE_NAME))))];
+        for entry in WalkDir::new(folder).min_depth(2).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() || !entry.file_name().eq_ignore_ascii_case(IGNORE_FILE_NAME) {
+                continue;
+            }
+            let Some(dir) = entry.path().parent() else { continue };
+            let Ok(rel) = dir.strip_prefix(folder) else { continue };
+            let key = PathBuf::from(rel.to_string_lossy().to_lowercase());
+            matchers.push((key, build_matcher(dir, &[], Some(entry.path()))));
+        }
+        matchers.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+
+        BundleIgnore { matchers, output: build_matcher(folder, OUTPUT_PATTERNS, None) }
+    }
+
+    /// Check a file path relative to the folder (either separator style)
+    pub fn is_ignored(&self, rel: &str) -> bool {
+        let rel = PathBuf::from(rel.replace('\\', "/"));
+        if self.output.matched_path_or_any_parents(&rel, false).is_ignore() {
+            return true;
+        }
+        let lower = PathBuf::from(rel.to_string_lossy().to_lowercase());
+        for (dir, matcher) in &self.matchers {
+            let Ok(inner) = lower.strip_prefix(dir) else { continue };
+            match matcher.matched_path_or_any_parents(inner, false) {
+                Match::None => continue,
+                found => return found.is_ignore(),
+            }
+        }
+        false
+    }
+
+    /// Check a file path relative to the OMSI root that lies inside `folder_rel`
+    pub fn is_ignored_in(&self, folder_rel: &str, file_rel: &str) -> bool {
+        let folder_key = normalize_key(folder_rel);
+        let file_key = normalize_key(file_rel);
+        match file_key.strip_prefix(&folder_key) {
+            Some(rest) => self.is_ignored(rest.trim_start_matches('\\')),
+            None => false,
+        }
+    }
+}
+
+// Case-insensitive rules for `folder` from `patterns` and an ignore file
+fn build_matcher(folder: &Path, patterns: &[&str], ignore_file: Option<&Path>) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(folder);
+    builder.case_insensitive(true).ok();
+    for pattern in patterns {
+        let _ = builder.add_line(None, pattern);
+    }
+    if let Some(ignore_file) = ignore_file.filter(|f| f.is_file()) {
+        if let Some(e) = builder.add(ignore_file) {
+            log_warn!("Invalid pattern in {}: {}", ignore_file.display(), e);
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        log_warn!("Failed to build ignore rules for {}: {}", folder.display(), e);
+        Gitignore::empty()
+    })
+}
+
+/// Compare two absolute paths the way Windows does (case-insensitive, either separator)
+pub fn same_path(a: &Path, b: &Path) -> bool {
+    let normalize = |p: &Path| p.to_string_lossy().replace('\\', "/").to_lowercase();
+    normalize(a) == normalize(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::test_utils::{test_dir, write_files};
+
+    fn load(name: &str, files: &[(&str, &str)]) -> (PathBuf, BundleIgnore) {
+        let folder = test_dir(&format!("ignore_{}", name));
+        write_files(&folder, files);
+        let ignore = BundleIgnore::load(&folder);
+        (folder, ignore)
+    }
+
+    #[test]
+    fn applies_default_exclusions() {
+        let (folder, ignore) = load("defaults", &[]);
+        assert!(ignore.is_ignored("tiles\\tile_0_0.map.BAK"));
+        assert!(ignore.is_ignored("Backup\\global.cfg"));
+        assert!(ignore.is_ignored("Thumbs.db"));
+        assert!(ignore.is_ignored("MyMap.part2.zip"));
+        assert!(!ignore.is_ignored("tile_0_0.map"));
+        assert!(!ignore.is_ignored("texture\\road.dds"));
+        let _ = fs::remove_dir_all(&folder);
+    }
+
+    #[test]
+    fn negation_cannot_include_earlier_bundles() {
+        let (folder, ignore) = load("negation", &[(".bundleignore", "notes.txt\n!*.bak\n!*.zip\n!old/\n")]);
+        assert!(ignore.is_ignored("notes.txt"));
+        assert!(!ignore.is_ignored("global.cfg.bak"));
+        assert!(ignore.is_ignored("MyMap.zip"));
+        assert!(ignore.is_ignored("old\\MyMap.7z"));
+        let _ = fs::remove_dir_all(&folder);
+    }
+
+    #[test]
+    fn nested_files_match_relative_to_their_folder() {
+        let (folder, ignore) = load("nested", &[
+            (".bundleignore", "*.psd\n"),
+            ("Texture/.bundleignore", "/raw/\n!keep.psd\n"),
+        ]);
+        assert!(ignore.is_ignored("texture\\raw\\road.dds"));
+        assert!(!ignore.is_ignored("raw\\road.dds"));
+        assert!(ignore.is_ignored("Texture\\road.psd"));
+        assert!(!ignore.is_ignored("Texture\\keep.psd"));
+        assert!(ignore.is_ignored("keep.psd"));
+        assert!(ignore.is_ignored_in("Maps\\MyMap", "maps\\mymap\\Texture\\Raw\\a.dds"));
+        let _ = fs::remove_dir_all(&folder);
+    }
+}
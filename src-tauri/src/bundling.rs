@@ -1,14 +1,45 @@
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::collections::HashSet;
-use crate::types::{BundleRequest, BundleResult};
+use crate::types::{AddonFolder, BundleRequest, BundleResult, Confidence, Dependency, DependencyEntry, DependencySource, PlanOverrides};
+use crate::plan::load_overrides;
 use crate::phase1_collection;
 use crate::phase2_processing;
-use crate::utils::{copy_file_with_folders, create_zip};
+use crate::phase2_extraction::context::ExtractionContext;
+use crate::parse_cache::ParseCache;
+use crate::diagnostics::{self, LogLevel};
+use crate::bundle_ignore::{BundleIgnore, same_path};
+use crate::encoding::CodePages;
+use crate::archive_names::{FilenamePolicy, non_ascii_names, colliding_names};
+use crate::utils::{check_free_space, copy_file_with_folders};
+use crate::archive::{ArchiveFormat, ArchiveOptions, create_archive, source_date_epoch};
+use crate::manifest::{build_maps_manifest, build_sources_manifest, maps_manifest_name, sources_manifest_name};
+use crate::omsi_root::{detect_omsi_root, map_rel_path};
+use crate::file_index::normalize_key;
+use crate::volumes::{Volume, build_manifest, manifest_name, plan_volumes, volume_entries, volume_path};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use rayon::prelude::*;
 use walkdir::WalkDir;
 
+// How often a split bundle is planned again when a volume ends up over the size limit
+const MAX_SPLIT_ATTEMPTS: usize = 3;
+
+/// Number of bundle temp folders created by this process, to keep them apart
+static TEMP_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// New temporary folder for the files of a bundle; every run gets its own, so the same map
+/// can be bundled twice at once
+fn bundle_temp_dir(map_name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "omsi_bundle_{}_{}_{}",
+        map_name,
+        std::process::id(),
+        TEMP_DIR_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
 pub type ProgressCallback = Box<dyn Fn(&str, f32) + Send + Sync>;
 
 fn emit_progress(cb: &Option<Arc<ProgressCallback>>, message: &str, progress: f32) {
@@ -17,118 +48,671 @@ fn emit_progress(cb: &Option<Arc<ProgressCallback>>, message: &str, progress: f3
     }
 }
 
-// Create the bundle ZIP file with all dependencies
-pub fn create_bundle(request: BundleRequest, progress_cb: Option<Arc<ProgressCallback>>) -> BundleResult {
-    let map_path = Path::new(&request.map_folder);
+/// Minimum time between two progress updates while an archive is written
+const ARCHIVE_PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+// Progress callback for the archive writers, mapping (entries done, total) onto `start..start + span`.
+// Updates are throttled by time; the last entry is always reported.
+fn archive_progress(
+    progress_cb: &Option<Arc<ProgressCallback>>,
+    message: String,
+    start: f32,
+    span: f32,
+) -> impl Fn(usize, usize) + '_ {
+    let last: Mutex<Option<Instant>> = Mutex::new(None);
+    move |done, total| {
+        let mut last = last.lock().unwrap();
+        if done < total && last.is_some_and(|at| at.elapsed() < ARCHIVE_PROGRESS_INTERVAL) {
+            return;
+        }
+        *last = Some(Instant::now());
+        emit_progress(progress_cb, &message, start + span * (done as f32 / total.max(1) as f32));
+    }
+}
+
+fn failure(error: String) -> BundleResult {
+    BundleResult {
+        success: false,
+        output_path: None,
+        output_paths: Vec::new(),
+        error: Some(error),
+        scan_report: None,
+        non_ascii_names: Vec::new(),
+        heuristic_dependencies: Vec::new(),
+        dependencies: Vec::new(),
+    }
+}
+
+// Write the archive, split into volumes of at most `max_volume_size` bytes when given.
+// Volumes are planned from estimated sizes; when one still ends up too big, the
+// bundle is planned again with the excess taken off the limit.
+pub(crate) fn write_bundle(
+    temp_dir: &Path,
+    output_path: &Path,
+    archive_format: ArchiveFormat,
+    max_volume_size: Option<u64>,
+    options: &ArchiveOptions,
+    progress_cb: &Option<Arc<ProgressCallback>>,
+) -> Result<Vec<PathBuf>, String> {
+    let Some(max_size) = max_volume_size else {
+        return write_single(temp_dir, output_path, archive_format, options, progress_cb);
+    };
+    
+    let mut limit = max_size;
+    for _ in 0..MAX_SPLIT_ATTEMPTS {
+        let volumes = plan_volumes(temp_dir, limit)?;
+        let paths = if volumes.len() > 1 {
+            write_volumes(temp_dir, output_path, archive_format, &volumes, options, progress_cb)?
+        } else {
+            write_single(temp_dir, output_path, archive_format, options, progress_cb)?
+        };
+        
+        let largest = paths.iter().filter_map(|p| fs::metadata(p).ok()).map(|m| m.len()).max().unwrap_or(0);
+        if largest <= max_size {
+            return Ok(paths);
+        }
+        log_warn!("A volume is {} bytes over the maximum size, splitting again", largest - max_size);
+        for path in &paths {
+            let _ = fs::remove_file(path);
+        }
+        limit = limit.saturating_sub(largest - max_size);
+    }
+    Err(format!("Could not split the bundle into volumes of at most {} MB", max_size / (1024 * 1024)))
+}
+
+// Write the whole bundle into one archive, with a manifest of all its files
+fn write_single(
+    temp_dir: &Path,
+    output_path: &Path,
+    archive_format: ArchiveFormat,
+    options: &ArchiveOptions,
+    progress_cb: &Option<Arc<ProgressCallback>>,
+) -> Result<Vec<PathBuf>, String> {
+    let mut entries: Vec<PathBuf> = WalkDir::new(temp_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .collect();
+    let file_name = output_path.file_name().and_then(|s| s.to_str()).unwrap_or("bundle");
+    let volume = Volume { files: entries.iter().filter(|p| p.is_file()).cloned().collect(), size: 0 };
+    let manifest_path = write_manifest(temp_dir, &archive_format.stem(file_name), 1, 1, &volume)?;
+    entries.push(manifest_path.clone());
+    
+    let result = create_archive(
+        archive_format,
+        temp_dir,
+        &entries,
+        output_path,
+        options,
+        &archive_progress(progress_cb, "Compressing bundle...".to_string(), 0.95, 0.05),
+    )
+    .map(|_| vec![output_path.to_path_buf()])
+    .map_err(|e| format!("Failed to create archive: {}", e));
+    let _ = fs::remove_file(&manifest_path);
+    result
+}
+
+// Write the manifest of a volume into the bundle folder, to be archived with it
+fn write_manifest(temp_dir: &Path, bundle: &str, index: usize, count: usize, volume: &Volume) -> Result<PathBuf, String> {
+    let manifest = build_manifest(temp_dir, bundle, index, count, volume);
+    let path = temp_dir.join(manifest_name(bundle, index, count));
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write bundle manifest: {}", e))?;
+    Ok(path)
+}
+
+/// Write one self-contained archive per volume, each with its own manifest
+fn write_volumes(
+    temp_dir: &Path,
+    output_path: &Path,
+    archive_format: ArchiveFormat,
+    volumes: &[Volume],
+    options: &ArchiveOptions,
+    progress_cb: &Option<Arc<ProgressCallback>>,
+) -> Result<Vec<PathBuf>, String> {
+    let file_name = output_path.file_name().and_then(|s| s.to_str()).unwrap_or("bundle");
+    let bundle = archive_format.stem(file_name);
+    let mut paths = Vec::new();
     
-    // Find OMSI 2 root folder (should be 2 levels up from map folder: OMSI 2/maps/mapname)
-    let omsi_root = match map_path.parent().and_then(|p| p.parent()) {
-        Some(root) => root,
-        None => return BundleResult {
-            success: false,
-            output_path: None,
-            error: Some("Could not determine OMSI 2 root folder".to_string()),
+    for (i, volume) in volumes.iter().enumerate() {
+        let index = i + 1;
+        emit_progress(
+            progress_cb,
+            format!("Compressing volume {} of {}...", index, volumes.len()).as_str(),
+            0.95 + 0.05 * (i as f32 / volumes.len() as f32),
+        );
+        
+        let manifest_path = write_manifest(temp_dir, &bundle, index, volumes.len(), volume)?;
+        let mut entries = volume_entries(temp_dir, volume);
+        entries.push(manifest_path.clone());
+        let path = volume_path(output_path, archive_format, index);
+        let start = 0.95 + 0.05 * (i as f32 / volumes.len() as f32);
+        let result = create_archive(
+            archive_format,
+            temp_dir,
+            &entries,
+            &path,
+            options,
+            &archive_progress(
+                progress_cb,
+                format!("Compressing volume {} of {}...", index, volumes.len()),
+                start,
+                0.05 / volumes.len() as f32,
+            ),
+        );
+        let _ = fs::remove_file(&manifest_path);
+        result.map_err(|e| format!("Failed to create volume {}: {}", index, e))?;
+        
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        log_info!("Volume {} of {}: {} files, {} MB", index, volumes.len(), volume.files.len(), size / (1024 * 1024));
+        paths.push(path);
+    }
+    
+    // Remove leftover volumes of a previous, larger bundle with the same name
+    let mut index = volumes.len() + 1;
+    while volume_path(output_path, archive_format, index).is_file() {
+        let _ = fs::remove_file(volume_path(output_path, archive_format, index));
+        index += 1;
+    }
+    
+    Ok(paths)
+}
+
+// Normalize a mount path inside the archive (`Sceneryobjects\MyObjects`).
+// None for absolute paths and paths leaving the archive root.
+fn mount_path(mount: &str) -> Option<String> {
+    let parts: Vec<&str> = mount.split(['\\', '/']).filter(|p| !p.is_empty() && *p != ".").collect();
+    if parts.is_empty() || parts.contains(&"..") || mount.contains(':') || mount.starts_with(['\\', '/']) {
+        return None;
+    }
+    Some(parts.join("\\"))
+}
+
+// All files of a folder as (source, path in the bundle below `mount`),
+// skipping ignored files and the bundle being written
+fn mounted_files(folder: &Path, mount: &str, output_path: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let ignore = BundleIgnore::load(folder);
+    let dest = PathBuf::from(mount.replace('\\', "/"));
+    WalkDir::new(folder)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| !same_path(e.path(), output_path))
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(folder).ok()?;
+            (!ignore.is_ignored(&rel.to_string_lossy())).then(|| (e.path().to_path_buf(), dest.join(rel)))
+        })
+        .collect()
+}
+
+// Initial dependencies of an asset pack: the listed files, and every file of the listed folders
+// (so their objects, splines and vehicles are resolved too) plus the folders themselves
+fn collect_asset_dependencies(assets: &[String], omsi_root: &Path, ctx: &ExtractionContext) -> Result<HashSet<Dependency>, String> {
+    let mut deps = HashSet::new();
+    for asset in assets {
+        let path = Path::new(asset);
+        let rel = if path.is_absolute() {
+            path.strip_prefix(omsi_root)
+                .map_err(|_| format!("{} is not inside the OMSI folder", asset))?
+                .to_string_lossy()
+                .replace('/', "\\")
+        } else {
+            asset.replace('/', "\\")
+        };
+        let rel = rel.trim_matches('\\');
+        
+        if ctx.index.is_dir(rel) {
+            deps.insert(Dependency::Folder(ctx.index.real_path(rel).unwrap_or_else(|| rel.to_string())));
+            deps.extend(ctx.index.files_under(Path::new(rel)).into_iter().map(Dependency::File));
+        } else if ctx.index.is_file(rel) {
+            deps.insert(Dependency::File(ctx.index.real_path(rel).unwrap_or_else(|| rel.to_string())));
+        } else {
+            return Err(format!("Asset not found in the OMSI folder: {}", asset));
         }
+    }
+    Ok(deps)
+}
+
+/// A bundle request resolved up to phase 1
+pub(crate) struct PreparedBundle {
+    pub omsi_root: PathBuf,
+    pub asset_pack: bool,
+    /// Map folder name, or the name of the first asset for asset packs
+    pub name: String,
+    /// Every map as (folder, name, folder in the bundle relative to the OMSI root)
+    pub maps: Vec<(PathBuf, String, String)>,
+    pub asset_roots: Vec<PathBuf>,
+    pub ctx: ExtractionContext,
+    /// Saved plan overrides of all bundled maps
+    pub overrides: PlanOverrides,
+    /// Phase 1 results, one set per map (or the asset pack)
+    pub initial_deps: Vec<HashSet<Dependency>>,
+}
+
+// Find the OMSI root and the maps of a request, index the asset folders and run phase 1
+pub(crate) fn prepare_bundle(request: &BundleRequest, progress_cb: &Option<Arc<ProgressCallback>>) -> Result<PreparedBundle, String> {
+    let map_path = Path::new(&request.map_folder);
+    
+    // Asset packs have no map folder, everything is resolved from the listed root files and folders
+    let asset_pack = request.map_folder.is_empty();
+    let assets = request.assets.clone().unwrap_or_default();
+    if asset_pack && assets.is_empty() {
+        return Err("Select a map folder or the assets to bundle".to_string());
+    }
+    if !asset_pack && !assets.is_empty() {
+        return Err("Assets can only be bundled without a map folder".to_string());
+    }
+    
+    // Use the given OMSI 2 root folder, otherwise look for it above the map folder.
+    // Fall back to 2 levels up from the map folder (OMSI 2/maps/mapname).
+    let omsi_root = match request.omsi_root.as_deref() {
+        Some(root) => PathBuf::from(root),
+        None if asset_pack => return Err("An OMSI 2 root folder is required for asset packs".to_string()),
+        None => match detect_omsi_root(map_path).or_else(|| map_path.parent().and_then(|p| p.parent()).map(Path::to_path_buf)) {
+            Some(root) => root,
+            None => return Err("Could not determine OMSI 2 root folder".to_string()),
+        },
     };
+    log_info!("OMSI root: {}", omsi_root.display());
+    let omsi_root = omsi_root.as_path();
     
-    // Get map folder name
-    let map_name = match map_path.file_name().and_then(|n| n.to_str()) {
+    // Get map folder name (or the name of the first asset for asset packs)
+    let map_name = if asset_pack { Path::new(&assets[0]).file_stem() } else { map_path.file_name() };
+    let map_name = match map_name.and_then(|n| n.to_str()) {
         Some(name) => name,
-        None => return BundleResult {
-            success: false,
-            output_path: None,
-            error: Some("Invalid map folder name".to_string()),
-        }
+        None => return Err("Invalid map folder name".to_string()),
     };
     
-    println!("Bundling map: {}", map_name);
+    // Every map as (folder, name, folder in the bundle relative to the OMSI root).
+    // Further maps of a series share the OMSI root of the first one.
+    let mut maps: Vec<(PathBuf, String, String)> = Vec::new();
+    if !asset_pack {
+        maps.push((map_path.to_path_buf(), map_name.to_string(), map_rel_path(omsi_root, map_path, map_name)));
+    }
+    for folder in request.map_folders.iter().flatten().filter(|_| !asset_pack) {
+        let path = PathBuf::from(folder);
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => return Err(format!("Invalid map folder name: {}", folder)),
+        };
+        if detect_omsi_root(&path).map(|root| !same_path(&root, omsi_root)).unwrap_or(false) {
+            return Err(format!("{} is not in the same OMSI folder as {}", folder, map_name));
+        }
+        if maps.iter().any(|(other, _, _)| same_path(other, &path)) {
+            continue;
+        }
+        let rel = map_rel_path(omsi_root, &path, &name);
+        if let Some((_, other, _)) = maps.iter().find(|(_, _, other_rel)| other_rel.eq_ignore_ascii_case(&rel)) {
+            return Err(format!("{} and {} would both be bundled as {}", name, other, rel));
+        }
+        maps.push((path, name, rel));
+    }
     
-    // Phase 1: Collect initial dependencies from map files
-    emit_progress(&progress_cb, "Scanning map configuration...", 0.01);
-    let initial_deps = match phase1_collection::collect_all_dependencies(map_path) {
-        Ok(deps) => deps,
-        Err(e) => return BundleResult {
-            success: false,
-            output_path: None,
-            error: Some(format!("Map scan failed: {}", e)),
+    if asset_pack {
+        log_info!("Bundling asset pack: {}", assets.join(", "));
+    } else {
+        log_info!("Bundling map: {}", maps.iter().map(|(_, name, _)| name.as_str()).collect::<Vec<_>>().join(", "));
+    }
+    
+    let code_pages = CodePages::new(omsi_root, request.code_page.as_deref(), request.folder_code_pages.as_ref())?;
+    
+    // Heuristic sources the user turned off
+    let mut disabled_sources = Vec::new();
+    for name in request.disabled_sources.iter().flatten() {
+        match DependencySource::parse(name) {
+            Ok(DependencySource::Section) => return Err("Exact section hits cannot be disabled".to_string()),
+            Ok(source) => disabled_sources.push(source),
+            Err(e) => return Err(e),
         }
+    }
+    
+    // Secondary asset roots, searched in order for files missing in the OMSI root
+    let asset_roots: Vec<PathBuf> = request.asset_roots.iter().flatten().map(PathBuf::from).collect();
+    if let Some(missing) = asset_roots.iter().find(|root| !root.is_dir()) {
+        return Err(format!("Asset root not found: {}", missing.display()));
+    }
+    
+    // Index the OMSI asset folders once so every lookup below is served from memory
+    emit_progress(progress_cb, "Indexing OMSI folders...", 0.0);
+    let mut ctx = ExtractionContext::with_fallback_roots(omsi_root, &asset_roots, ParseCache::load_default(), code_pages);
+    if !disabled_sources.is_empty() {
+        log_info!("Disabled dependency sources: {:?}", disabled_sources);
+    }
+    ctx.disabled_sources = disabled_sources;
+    
+    // Phase 1: Collect initial dependencies from map files, separately for every map
+    emit_progress(progress_cb, "Scanning map configuration...", 0.01);
+    let mut map_initial_deps = Vec::new();
+    for (path, name, rel) in &maps {
+        let map_code_page = ctx.code_pages.for_path(rel);
+        match phase1_collection::collect_all_dependencies(path, &ctx.cache, map_code_page) {
+            Ok(found) => {
+                ctx.provenance.record(&found);
+                map_initial_deps.push(found.into_enabled(&ctx.disabled_sources));
+            }
+            Err(e) => return Err(format!("Map scan of {} failed: {}", name, e)),
+        }
+    }
+    if asset_pack {
+        match collect_asset_dependencies(&assets, omsi_root, &ctx) {
+            Ok(deps) => map_initial_deps.push(deps),
+            Err(e) => return Err(e),
+        }
+    }
+    
+    // Manual corrections saved for the maps
+    let mut overrides = PlanOverrides::default();
+    for (path, _, _) in &maps {
+        let saved = load_overrides(path);
+        overrides.excluded.extend(saved.excluded);
+        overrides.included.extend(saved.included);
+    }
+    if !overrides.excluded.is_empty() || !overrides.included.is_empty() {
+        log_info!("Plan overrides: {} excluded, {} forced", overrides.excluded.len(), overrides.included.len());
+    }
+    
+    Ok(PreparedBundle {
+        omsi_root: omsi_root.to_path_buf(),
+        asset_pack,
+        name: map_name.to_string(),
+        maps,
+        asset_roots,
+        ctx,
+        overrides,
+        initial_deps: map_initial_deps,
+    })
+}
+
+// Phase 2 for every set of initial dependencies, honouring the plan overrides: excluded paths are
+// neither followed nor bundled, forced paths are resolved like map references (as an extra last set).
+// Returns the resolved sets and the excluded paths that were dropped.
+pub(crate) fn resolve_dependencies(
+    initial_deps: Vec<HashSet<Dependency>>,
+    overrides: &PlanOverrides,
+    omsi_root: &Path,
+    ctx: &ExtractionContext,
+) -> (Vec<HashSet<Dependency>>, HashSet<Dependency>) {
+    let mut excluded = HashSet::new();
+    let mut sets = Vec::new();
+    for deps in initial_deps {
+        let (dropped, kept): (HashSet<Dependency>, HashSet<Dependency>) = deps.into_iter().partition(|d| overrides.is_excluded(d.path()));
+        excluded.extend(dropped);
+        sets.push(kept);
+    }
+    
+    if !overrides.included.is_empty() {
+        let mut forced = HashSet::new();
+        for path in &overrides.included {
+            match collect_asset_dependencies(std::slice::from_ref(path), omsi_root, ctx) {
+                Ok(deps) => forced.extend(deps),
+                Err(e) => log_warn!("Forced path skipped: {}", e),
+            }
+        }
+        sets.push(forced);
+    }
+    
+    let resolved = sets.into_iter()
+        .map(|deps| {
+            let (dropped, kept): (HashSet<Dependency>, HashSet<Dependency>) = phase2_processing::process_dependencies(deps, ctx)
+                .into_iter()
+                .partition(|d| overrides.is_excluded(d.path()));
+            excluded.extend(dropped);
+            kept
+        })
+        .collect();
+    (resolved, excluded)
+}
+
+// Every resolved or excluded dependency outside the map folders, sorted by path.
+// Files that do not exist are missing, or optional when only heuristics found them.
+fn dependency_entries(
+    deps: &HashSet<Dependency>,
+    excluded: &HashSet<Dependency>,
+    in_map_folders: &impl Fn(&str) -> bool,
+    ctx: &ExtractionContext,
+) -> Vec<DependencyEntry> {
+    let resolved = deps.iter().map(|dep| {
+        let path = dep.path().to_string();
+        match dep {
+            Dependency::Folder(_) if ctx.index.is_dir(&path) => Dependency::Folder(path),
+            Dependency::File(_) if ctx.index.is_file(&path) => Dependency::File(path),
+            _ if ctx.provenance.source(&path).confidence() == Confidence::Heuristic => Dependency::Optional(path),
+            _ => Dependency::Missing(path),
+        }
+    });
+    let dropped = excluded.iter().map(|dep| Dependency::Excluded(dep.path().to_string()));
+    
+    let mut entries: Vec<DependencyEntry> = resolved.chain(dropped)
+        .filter(|dep| !in_map_folders(dep.path()))
+        .map(|dependency| DependencyEntry {
+            category: dependency.category(),
+            source: ctx.provenance.source(dependency.path()),
+            dependency,
+        })
+        .collect();
+    entries.sort_by(|a, b| (a.dependency.path(), &a.dependency).cmp(&(b.dependency.path(), &b.dependency)));
+    entries.dedup_by(|a, b| a.dependency == b.dependency);
+    entries
+}
+
+// Create the bundle ZIP file with all dependencies
+pub fn create_bundle(request: BundleRequest, progress_cb: Option<Arc<ProgressCallback>>) -> BundleResult {
+    let map_path = Path::new(&request.map_folder);
+    
+    // The requested level only applies to this run
+    let _level = request.log_level.as_deref().and_then(LogLevel::parse).map(diagnostics::scoped_level);
+    
+    let archive_format = match request.archive_format.as_deref().map(ArchiveFormat::parse) {
+        Some(Ok(format)) => format,
+        Some(Err(e)) => return failure(e),
+        None => ArchiveFormat::Zip,
     };
+    
+    let filename_policy = match request.filename_policy.as_deref().map(FilenamePolicy::parse) {
+        Some(Ok(policy)) => policy,
+        Some(Err(e)) => return failure(e),
+        None => FilenamePolicy::Utf8,
+    };
+    
+    // Asset packs have no map folder to write into, and archives don't belong in the OMSI root
+    if request.map_folder.is_empty() && request.output_folder.is_none() {
+        return failure("An output folder is required for asset packs".to_string());
+    }
+    
+    let PreparedBundle { omsi_root, asset_pack, name: map_name, maps, asset_roots, ctx, overrides, initial_deps: map_initial_deps } =
+        match prepare_bundle(&request, &progress_cb) {
+            Ok(prepared) => prepared,
+            Err(e) => return failure(e),
+        };
+    let omsi_root = omsi_root.as_path();
+    let map_name = map_name.as_str();
+    let initial_deps: HashSet<Dependency> = map_initial_deps.iter().flatten().cloned().collect();
     emit_progress(&progress_cb, format!("Map scan complete: {} files found", initial_deps.len()).as_str(), 0.1);
     
+    // Build the scan report if requested (never written into the map folder)
+    let scan_report = if request.include_scan_report.unwrap_or(false) || request.scan_report_path.is_some() {
+        let files = initial_deps.iter().filter(|d| !d.is_folder()).map(Dependency::path);
+        let report = diagnostics::build_scan_report(files, if asset_pack { omsi_root } else { map_path });
+        log_debug!("{}", diagnostics::format_scan_report(&report));
+        if let Some(report_path) = &request.scan_report_path {
+            if let Err(e) = fs::write(report_path, diagnostics::format_scan_report(&report)) {
+                log_warn!("Failed to write scan report to {}: {}", report_path, e);
+            }
+        }
+        request.include_scan_report.unwrap_or(false).then_some(report)
+    } else {
+        None
+    };
+    
     // Create temp folder
-    let temp_dir = std::env::temp_dir().join(format!("omsi_bundle_{}", map_name));
-    if temp_dir.exists() {
-        let _ = fs::remove_dir_all(&temp_dir);
-    }
+    let temp_dir = bundle_temp_dir(map_name);
     if let Err(e) = fs::create_dir_all(&temp_dir) {
-        return BundleResult {
-            success: false,
-            output_path: None,
-            error: Some(format!("Failed to create temp folder: {}", e)),
-        };
+        return failure(format!("Failed to create temp folder: {}", e));
     }
     
     // Phase 2: Process dependencies recursively
     emit_progress(&progress_cb, "Analyzing dependencies...", 0.1);
-    let all_deps = phase2_processing::process_dependencies(initial_deps, omsi_root);
+    let (map_deps, excluded) = resolve_dependencies(map_initial_deps, &overrides, omsi_root, &ctx);
+    let all_deps: HashSet<Dependency> = map_deps.iter().flatten().cloned().collect();
+    if !excluded.is_empty() {
+        log_info!("{} dependencies excluded by the plan overrides", excluded.len());
+    }
+    if let Err(e) = ctx.cache.save() {
+        log_warn!("Failed to save parse cache: {}", e);
+    }
     
-    println!("Resolved {} total dependencies", all_deps.len());
+    log_info!("Resolved {} total dependencies", all_deps.len());
+
     emit_progress(&progress_cb, format!("Analysis complete: {} total files", all_deps.len()).as_str(), 0.3);
     
+    // Determine output path
+    let zip_name = request.zip_name.clone().unwrap_or_else(|| map_name.to_string());
+    
+    // Ensure zip_name has the extension of the archive format
+    let zip_name = archive_format.with_extension(&zip_name);
+    
+    let output_path = match &request.output_folder {
+        Some(out_folder) => PathBuf::from(out_folder).join(&zip_name),
+        None => map_path.join(&zip_name),
+    };
+    
     // Separate folders from files and flatten folders
     let mut files_to_copy = HashSet::new();
     let mut folders_to_process = Vec::new();
     
-    // Explicitly add the map folder to ensure all its content is copied
-    if let Ok(map_rel_path) = map_path.strip_prefix(omsi_root) {
-        folders_to_process.push(map_rel_path.to_string_lossy().replace("\\", "/"));
+    // Explicitly add the map folders to ensure all their content is copied
+    for (path, _, _) in &maps {
+        if let Ok(rel) = path.strip_prefix(omsi_root) {
+            folders_to_process.push(rel.to_string_lossy().replace("\\", "/"));
+        }
     }
 
-    // Explicitly add the addon folder if specified
+    // Explicitly add the addon folders if specified. Inside the OMSI root they keep their path,
+    // elsewhere (or when a mount path is given) they are copied to the mount path in the archive.
+    let mut addons: Vec<AddonFolder> = request.addon_folders.clone().unwrap_or_default();
     if let Some(addon_folder) = &request.addon_folder {
-        let addon_path = Path::new(addon_folder);
-        if let Ok(addon_rel_path) = addon_path.strip_prefix(omsi_root) {
-            folders_to_process.push(addon_rel_path.to_string_lossy().replace("\\", "/"));
+        addons.insert(0, AddonFolder { path: addon_folder.clone(), mount: None });
+    }
+    let mut addon_mounts: Vec<(PathBuf, String)> = Vec::new();
+    for addon in &addons {
+        let addon_path = Path::new(&addon.path);
+        let ignored = if !addon_path.is_dir() {
+            Some("the folder does not exist".to_string())
+        } else {
+            match (addon.mount.as_deref(), addon_path.strip_prefix(omsi_root)) {
+                (Some(mount), _) => match mount_path(mount) {
+                    Some(mount) => {
+                        addon_mounts.push((addon_path.to_path_buf(), mount));
+                        None
+                    }
+                    None => Some(format!("invalid mount path {}", mount)),
+                },
+                (None, Ok(addon_rel_path)) => {
+                    folders_to_process.push(addon_rel_path.to_string_lossy().replace("\\", "/"));
+                    None
+                }
+                (None, Err(_)) => Some("it is outside the OMSI folder and has no mount path".to_string()),
+            }
+        };
+        if let Some(reason) = ignored {
+            log_warn!("Addon folder {} ignored: {}", addon.path, reason);
+            emit_progress(&progress_cb, format!("Addon folder {} ignored: {}", addon.path, reason).as_str(), 0.3);
         }
     }
     
     for dep in &all_deps {
-        if dep.starts_with("FOLDER:") {
-            let folder_path = dep.strip_prefix("FOLDER:").unwrap();
-            folders_to_process.push(folder_path.to_string());
-        } else {
-            files_to_copy.insert(dep.clone());
+        match dep {
+            Dependency::Folder(folder_path) => folders_to_process.push(folder_path.clone()),
+            other => {
+                files_to_copy.insert(other.path().to_string());
+            }
         }
     }
 
     // Flatten folders into files for better parallelism
     // We use par_iter to walk multiple folders at once if there are many
+    // Each folder's .bundleignore (plus the default exclusions) is applied while flattening
     let folder_files: Vec<String> = folders_to_process.par_iter().flat_map(|folder_rel_path| {
-        let folder_abs_path = omsi_root.join(folder_rel_path);
-        let mut files = Vec::new();
-        if folder_abs_path.exists() {
-             for entry in WalkDir::new(&folder_abs_path).into_iter().filter_map(|e| e.ok()) {
-                 if entry.file_type().is_file() {
-                     // Get path relative to omsi_root
-                     if let Ok(rel) = entry.path().strip_prefix(omsi_root) {
-                         files.push(rel.to_string_lossy().replace("\\", "/"));
-                     }
-                 }
-             }
-        }
-        files
+        let ignore = BundleIgnore::load(&ctx.index.full_path(folder_rel_path));
+        ctx.index.files_under(Path::new(folder_rel_path))
+            .into_iter()
+            .filter(|f| !ignore.is_ignored_in(folder_rel_path, f))
+            .collect::<Vec<_>>()
     }).collect();
 
     files_to_copy.extend(folder_files);
 
-    // Filter out files that are inside the map folder itself (because we copy the whole map folder at the end)
-    // map_path is absolute. We need to check if omsi_root/file is inside map_path
-    // Or simpler: check if file path starts with "maps/mapname/"
-    let map_folder_prefix = format!("maps/{}/", map_name);
+    // Filter out files that are inside the map folders themselves (because we copy the whole map folders at the end)
+    // Check if the file path starts with the map folder relative to the OMSI root ("maps/mapname/").
+    // Mounted addon folders replace whatever the OMSI root has at their mount path.
+    let map_folder_prefixes: Vec<String> = maps.iter()
+        .map(|(_, _, rel)| rel)
+        .chain(addon_mounts.iter().map(|(_, mount)| mount))
+        .map(|rel| format!("{}/", rel.replace('\\', "/")).to_lowercase())
+        .collect();
+    let in_map_folders = |f: &str| {
+        let f = f.replace("\\", "/").to_lowercase();
+        map_folder_prefixes.iter().any(|prefix| f.starts_with(prefix))
+    };
+    // Differently cased references to one file are stored once, under its real name
+    let mut seen = HashSet::new();
     let final_files_list: Vec<String> = files_to_copy.into_iter()
-        .filter(|f| !f.replace("\\", "/").to_lowercase().starts_with(&map_folder_prefix.to_lowercase()))
+        .filter(|f| !in_map_folders(f))
+        .filter(|f| !same_path(&ctx.index.full_path(f), &output_path))
+        .filter(|f| !overrides.is_excluded(f))
+        .map(|f| ctx.index.real_path(&f).unwrap_or(f))
+        .filter(|f| seen.insert(normalize_key(f)))
         .collect();
     
+    // Everything only found by heuristics, for the user to review
+    let bundled: HashSet<String> = final_files_list.iter().map(|f| normalize_key(f)).collect();
+    let heuristic_dependencies = ctx.provenance.heuristic_tags(|path| bundled.contains(&normalize_key(path)));
+    if !heuristic_dependencies.is_empty() {
+        let skipped = heuristic_dependencies.iter().filter(|tag| !tag.bundled).count();
+        log_info!("{} dependencies found by heuristics ({} not bundled)", heuristic_dependencies.len(), skipped);
+        for tag in &heuristic_dependencies {
+            log_debug!("  [{:?}] {}", tag.source, tag.path);
+        }
+    }
+    
+    // What became of every dependency, for the bundle result
+    let dependencies = dependency_entries(&all_deps, &excluded, &in_map_folders, &ctx);
+    let missing: Vec<&str> = dependencies.iter()
+        .filter(|entry| matches!(entry.dependency, Dependency::Missing(_)))
+        .map(|entry| entry.dependency.path())
+        .collect();
+    if !missing.is_empty() {
+        log_warn!("{} referenced files or folders are missing", missing.len());
+        for path in &missing {
+            log_debug!("  missing: {}", path);
+        }
+    }
+    
+    // Collect all files in the map folders and mounted addon folders as (source, path in the bundle)
+    let mut map_files: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for (path, _, rel) in &maps {
+        map_files.extend(mounted_files(path, rel, &output_path));
+    }
+    for (path, mount) in &addon_mounts {
+        map_files.extend(mounted_files(path, mount, &output_path));
+    }
+    
+    // Check free space for the copied files and the archive before copying anything
+    let bundle_size: u64 = final_files_list.par_iter()
+        .filter_map(|f| fs::metadata(ctx.index.full_path(f)).ok())
+        .map(|m| m.len())
+        .sum::<u64>()
+        + map_files.par_iter()
+            .filter_map(|(p, _)| fs::metadata(p).ok())
+            .map(|m| m.len())
+            .sum::<u64>();
+    let output_dir = output_path.parent().unwrap_or(Path::new("."));
+    if let Err(e) = check_free_space(&temp_dir, output_dir, bundle_size) {
+        let _ = fs::remove_dir_all(&temp_dir);
+        return failure(e);
+    }
+    
     // Copy all files with progress (Parallel)
     let copied_files = Arc::new(Mutex::new(0));
     let failed_files = Arc::new(Mutex::new(Vec::new()));
@@ -137,11 +721,11 @@ pub fn create_bundle(request: BundleRequest, progress_cb: Option<Arc<ProgressCal
     emit_progress(&progress_cb, "Copying assets...", 0.3);
 
     final_files_list.par_iter().for_each(|file_path| {
-        let src = omsi_root.join(file_path);
-        let dest = temp_dir.join(file_path);
+        let src = ctx.index.full_path(file_path);
+        let dest = temp_dir.join(file_path.replace('\\', "/"));
         
         // Only attempt copy if source exists
-        if src.exists() {
+        if ctx.index.is_file(file_path) {
             if let Err(e) = copy_file_with_folders(&src, &dest) {
                 failed_files.lock().unwrap().push(format!("{}: {}", file_path, e));
             } else {
@@ -162,45 +746,61 @@ pub fn create_bundle(request: BundleRequest, progress_cb: Option<Arc<ProgressCal
     
     let final_copied_files = *copied_files.lock().unwrap();
     if total_files > 0 {
-        println!("Copied {} files", final_copied_files);
+        log_info!("Copied {} files", final_copied_files);
     }
     
-    // Copy entire map folder to temp/maps/mapname (Parallel)
+    // Copy entire map folders to temp/maps/mapname (Parallel)
     emit_progress(&progress_cb, "Copying map files...", 0.8);
-    let map_dest = temp_dir.join("maps").join(map_name);
-    
-    // Collect all files in map folder
-    let map_files: Vec<PathBuf> = WalkDir::new(map_path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .map(|e| e.path().to_path_buf())
-        .collect();
 
     let total_map_files = map_files.len().max(1);
     let copied_map_files = Arc::new(Mutex::new(0));
     
-    map_files.par_iter().for_each(|src_path| {
-        // Calculate relative path
-        if let Ok(rel_path) = src_path.strip_prefix(map_path) {
-            let dest_path = map_dest.join(rel_path);
+    map_files.par_iter().for_each(|(src_path, rel_path)| {
+        let dest_path = temp_dir.join(rel_path);
+        
+        if let Err(e) = copy_file_with_folders(src_path, &dest_path) {
+             failed_files.lock().unwrap().push(format!("Map file {}: {}", rel_path.display(), e));
+        } else {
+            let mut count = copied_map_files.lock().unwrap();
+            *count += 1;
             
-            if let Err(e) = copy_file_with_folders(src_path, &dest_path) {
-                 failed_files.lock().unwrap().push(format!("Map file {}: {}", rel_path.display(), e));
-            } else {
-                let mut count = copied_map_files.lock().unwrap();
-                *count += 1;
-                
-                if *count % 20 == 0 {
-                    let pct = 0.8 + 0.15 * (*count as f32 / total_map_files as f32);
-                    emit_progress(&progress_cb, "Copying map files...", pct);
-                }
+            if *count % 20 == 0 {
+                let pct = 0.8 + 0.15 * (*count as f32 / total_map_files as f32);
+                emit_progress(&progress_cb, "Copying map files...", pct);
             }
         }
     });
     
     emit_progress(&progress_cb, "Map files copied", 0.95);
     
+    // Record which map needs which asset when several maps share the bundle
+    if maps.len() > 1 {
+        let names: Vec<(&str, &str)> = maps.iter().map(|(_, name, rel)| (name.as_str(), rel.as_str())).collect();
+        let manifest = build_maps_manifest(&archive_format.stem(&zip_name), &names, &map_deps, &ctx.index);
+        let written = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(temp_dir.join(maps_manifest_name(&manifest.bundle)), json).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            log_warn!("Failed to write maps manifest: {}", e);
+        }
+        log_info!("{} assets are shared between maps", manifest.shared.len());
+    }
+    
+    // Record which root every asset came from when secondary roots were searched
+    if !asset_roots.is_empty() {
+        let roots: Vec<PathBuf> = std::iter::once(omsi_root.to_path_buf()).chain(asset_roots.iter().cloned()).collect();
+        let manifest = build_sources_manifest(&archive_format.stem(&zip_name), &roots, &final_files_list, &ctx.index);
+        for sources in manifest.roots.iter().skip(1) {
+            log_info!("{} files taken from {}", sources.files.len(), sources.root);
+        }
+        let written = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(temp_dir.join(sources_manifest_name(&manifest.bundle)), json).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            log_warn!("Failed to write sources manifest: {}", e);
+        }
+    }
+    
     // Copy README if specified
     if let Some(readme_path) = request.readme_path {
         let readme_src = Path::new(&readme_path);
@@ -210,61 +810,419 @@ pub fn create_bundle(request: BundleRequest, progress_cb: Option<Arc<ProgressCal
         }
     }
     
-    // Determine output path
-    let zip_name = request.zip_name.unwrap_or_else(|| format!("{}.zip", map_name));
-    
-    // Ensure zip_name has .zip extension
-    let zip_name = if !zip_name.to_lowercase().ends_with(".zip") {
-        format!("{}.zip", zip_name)
-    } else {
-        zip_name
-    };
-    
-    let output_path = if let Some(out_folder) = request.output_folder {
-        PathBuf::from(out_folder).join(&zip_name)
-    } else {
-        map_path.join(&zip_name)
-    };
-    
     let compression_method = request
         .compression_method
         .as_deref()
         .unwrap_or("deflate")
         .to_lowercase();
     let compression_level = request.compression_level.unwrap_or(1);
+    let fixed_mtime = request.reproducible.unwrap_or(false)
+        .then(|| source_date_epoch(request.source_date_epoch));
+    if let Some(mtime) = fixed_mtime {
+        log_info!("Reproducible mode, all entries dated {}", mtime);
+    }
+    let archive_options = ArchiveOptions {
+        compression_method,
+        compression_level,
+        filename_policy,
+        fixed_mtime,
+    };
 
-    println!("Creating ZIP file...");
+    // Report non-ASCII names before zipping, some unzip tools ignore the UTF-8 flag
+    let non_ascii_names = non_ascii_names(&temp_dir);
+    if !non_ascii_names.is_empty() {
+        log_warn!("{} file names contain non-ASCII characters", non_ascii_names.len());
+        for name in &non_ascii_names {
+            log_warn!("  {}", name);
+        }
+        emit_progress(&progress_cb, format!("{} file names contain non-ASCII characters", non_ascii_names.len()).as_str(), 0.95);
+        
+        let error = match filename_policy {
+            FilenamePolicy::Reject => Some(format!(
+                "{} file names contain non-ASCII characters, e.g. {}",
+                non_ascii_names.len(),
+                non_ascii_names[0]
+            )),
+            FilenamePolicy::Transliterate => {
+                let collisions = colliding_names(&temp_dir, filename_policy);
+                (!collisions.is_empty()).then(|| format!(
+                    "Transliterated file names would collide: {}",
+                    collisions.join(", ")
+                ))
+            }
+            FilenamePolicy::Utf8 => None,
+        };
+        if let Some(error) = error {
+            let _ = fs::remove_dir_all(&temp_dir);
+            return BundleResult { non_ascii_names, ..failure(error) };
+        }
+    }
+    
+    log_info!("Creating {} archive...", archive_format.extension());
     emit_progress(&progress_cb, "Compressing bundle...", 0.95);
     
-    // Create ZIP file
-    match create_zip(&temp_dir, &output_path, &compression_method, compression_level) {
-        Ok(_) => {
-            // Clean up temp folder
-            let _ = fs::remove_dir_all(&temp_dir);
+    // Create the archive (or one self-contained archive per volume)
+    let max_volume_size = request.max_volume_size_mb.map(|max_mb| max_mb.max(1) * 1024 * 1024);
+    let written = write_bundle(&temp_dir, &output_path, archive_format, max_volume_size, &archive_options, &progress_cb);
+    
+    // Clean up temp folder
+    let _ = fs::remove_dir_all(&temp_dir);
+    
+    match written {
+        Ok(paths) => {
             emit_progress(&progress_cb, "Done!", 1.0);
             
-            println!("Bundle created: {}", output_path.display());
+            for path in &paths {
+                log_info!("Bundle created: {}", path.display());
+            }
+            
+            let failed = failed_files.lock().unwrap();
+            for failure in failed.iter() {
+                log_warn!("Failed to copy {}", failure);
+            }
             
             BundleResult {
                 success: true,
-                output_path: Some(output_path.to_string_lossy().to_string()),
-                error: {
-                    let failed = failed_files.lock().unwrap();
-                    if failed.is_empty() {
-                        None
-                    } else {
-                        Some(format!("Bundle created, but {} files failed to copy", failed.len()))
-                    }
-                }
+                output_path: paths.first().map(|p| p.to_string_lossy().to_string()),
+                output_paths: paths.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+                error: if failed.is_empty() {
+                    None
+                } else {
+                    Some(format!("Bundle created, but {} files failed to copy", failed.len()))
+                },
+                scan_report,
+                non_ascii_names,
+                heuristic_dependencies,
+                dependencies,
             }
         }
-        Err(e) => {
-            let _ = fs::remove_dir_all(&temp_dir);
-            BundleResult {
-                success: false,
-                output_path: None,
-                error: Some(format!("Failed to create ZIP: {}", e)),
+        Err(e) => failure(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_dir, write_files};
+
+    // Bytes that do not compress (xorshift)
+    fn noise(len: usize, mut seed: u64) -> Vec<u8> {
+        (0..len).map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as u8
+        }).collect()
+    }
+
+    fn zip_options() -> ArchiveOptions {
+        ArchiveOptions {
+            compression_method: "deflate".to_string(),
+            compression_level: 1,
+            filename_policy: FilenamePolicy::Utf8,
+            fixed_mtime: None,
+        }
+    }
+
+    #[test]
+    fn reproducible_archives_are_byte_identical() {
+        let dir = test_dir("bundling_reproducible");
+        let files = [
+            "maps/Grundorf/global.cfg",
+            "maps/Grundorf/tile_0_0.map",
+            "Sceneryobjects/Houses/house.sco",
+            "Sceneryobjects/Houses/model/house.o3d",
+            "Texture/road.dds",
+        ];
+        // Same files, created in opposite order and with other modification times
+        let build = |name: &str, reversed: bool, age: u64| {
+            let src = dir.join(name);
+            let mut order: Vec<(usize, &str)> = files.iter().copied().enumerate().collect();
+            if reversed {
+                order.reverse();
+            }
+            for (i, file) in order {
+                let path = src.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, noise(5_000, i as u64 + 1)).unwrap();
+                let mtime = std::time::SystemTime::now() - std::time::Duration::from_secs(age + i as u64 * 7_000);
+                fs::File::options().write(true).open(&path).unwrap().set_modified(mtime).unwrap();
+            }
+            src
+        };
+        let first = build("first", false, 0);
+        let second = build("second", true, 90_000);
+
+        let options = ArchiveOptions { fixed_mtime: Some(source_date_epoch(Some(1_700_000_000))), ..zip_options() };
+        for format in [ArchiveFormat::Zip, ArchiveFormat::SevenZ, ArchiveFormat::TarZst] {
+            // Same bundle name (it is part of the manifest), different folders
+            let archive = |src: &Path, out: &str| {
+                let path = dir.join(out).join(format.with_extension("Bundle"));
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                write_bundle(src, &path, format, None, &options, &None).unwrap();
+                fs::read(path).unwrap()
+            };
+            assert_eq!(archive(&first, "out_first"), archive(&second, "out_second"), "{}", format.extension());
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn volumes_of_incompressible_data_stay_below_the_limit() {
+        let dir = test_dir("bundling_volume_limit");
+        let src = dir.join("bundle");
+        let mut total = 0;
+        for (i, folder) in ["Sceneryobjects/A", "Sceneryobjects/B", "Texture", "Sound/Bus"].iter().enumerate() {
+            for j in 0..6 {
+                let path = src.join(folder).join(format!("file_with_a_long_name_{}.dds", j));
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, noise(20_000, (i * 10 + j + 1) as u64)).unwrap();
+                total += 1;
             }
         }
+
+        let max_size = 100_000;
+        let paths = write_bundle(&src, &dir.join("Bundle.zip"), ArchiveFormat::Zip, Some(max_size), &zip_options(), &None).unwrap();
+        assert!(paths.len() > 1);
+
+        let mut files = 0;
+        for path in &paths {
+            assert!(fs::metadata(path).unwrap().len() <= max_size, "{}", path.display());
+            let archive = zip::ZipArchive::new(fs::File::open(path).unwrap()).unwrap();
+            files += archive.file_names().filter(|n| n.ends_with(".dds")).count();
+        }
+        assert_eq!(files, total);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn bundle(request: serde_json::Value) -> BundleResult {
+        let result = create_bundle(serde_json::from_value(request).unwrap(), None);
+        assert!(result.success, "{:?}", result.error);
+        result
+    }
+
+    fn archive_names(path: &str) -> Vec<String> {
+        let archive = zip::ZipArchive::new(fs::File::open(path).unwrap()).unwrap();
+        archive.file_names().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn bundles_every_run_in_its_own_folder() {
+        assert_ne!(bundle_temp_dir("TestMap"), bundle_temp_dir("TestMap"));
+    }
+
+    #[test]
+    fn applies_the_filename_policy() {
+        let dir = test_dir("bundling_names");
+        let root = dir.join("OMSI");
+        write_files(&root, &[
+            ("maps/TestMap/global.cfg", ""),
+            ("maps/TestMap/Café.txt", "cafe"),
+            ("maps/TestMap/Straße.txt", "street"),
+        ]);
+        let request = |policy: &str| serde_json::json!({
+            "map_folder": root.join("maps/TestMap"),
+            "output_folder": dir,
+            "filename_policy": policy,
+        });
+
+        let result = create_bundle(serde_json::from_value(request("reject")).unwrap(), None);
+        assert!(!result.success);
+        assert_eq!(result.non_ascii_names, vec!["maps/TestMap/Café.txt", "maps/TestMap/Straße.txt"]);
+
+        let result = bundle(request("transliterate"));
+        let names = archive_names(result.output_path.as_deref().unwrap());
+        assert!(names.iter().all(|name| name.is_ascii()), "{:?}", names);
+        assert!(names.contains(&"maps/TestMap/Cafe.txt".to_string()));
+        assert!(names.contains(&"maps/TestMap/Strasse.txt".to_string()));
+
+        // Names that would only differ by their diacritics are refused
+        write_files(&root, &[("maps/TestMap/cafe.txt", "other")]);
+        let result = create_bundle(serde_json::from_value(request("transliterate")).unwrap(), None);
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Café.txt"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn maps_share_assets_stored_once() {
+        let dir = test_dir("bundling_maps");
+        let root = dir.join("OMSI");
+        write_files(&root, &[
+            ("maps/North/global.cfg", "[groundtex]\nTexture\\shared.dds\n\n[groundtex]\nTexture\\north.dds\n"),
+            ("maps/South/global.cfg", "[groundtex]\ntexture\\SHARED.dds\n\n[groundtex]\nTexture\\south.dds\n"),
+            ("Texture/shared.dds", "shared"),
+            ("Texture/north.dds", "north"),
+            ("Texture/south.dds", "south"),
+            ("Texture/unused.dds", "unused"),
+        ]);
+
+        let result = bundle(serde_json::json!({
+            "map_folder": root.join("maps/North"),
+            "map_folders": [root.join("maps/South")],
+            "output_folder": dir,
+            "zip_name": "Region.zip",
+        }));
+        let names = archive_names(result.output_path.as_deref().unwrap());
+        assert_eq!(names.iter().filter(|n| n.eq_ignore_ascii_case("Texture/shared.dds")).count(), 1);
+        assert!(names.contains(&"maps/North/global.cfg".to_string()));
+        assert!(names.contains(&"maps/South/global.cfg".to_string()));
+        assert!(!names.contains(&"Texture/unused.dds".to_string()));
+
+        let mut archive = zip::ZipArchive::new(fs::File::open(result.output_path.unwrap()).unwrap()).unwrap();
+        let manifest: crate::manifest::MapsManifest = serde_json::from_reader(archive.by_name("Region.maps.json").unwrap()).unwrap();
+        assert_eq!(manifest.bundle, "Region");
+        assert_eq!(manifest.shared, vec!["Texture\\shared.dds".to_string()]);
+        let assets: Vec<(&str, &[String])> = manifest.maps.iter().map(|m| (m.map.as_str(), m.assets.as_slice())).collect();
+        assert_eq!(assets, vec![
+            ("North", &["Texture\\north.dds".to_string(), "Texture\\shared.dds".to_string()][..]),
+            ("South", &["Texture\\shared.dds".to_string(), "Texture\\south.dds".to_string()][..]),
+        ]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn asset_packs_resolve_dependencies_without_a_map() {
+        let dir = test_dir("bundling_asset_pack");
+        let root = dir.join("OMSI");
+        write_files(&root, &[
+            ("Splines/Roads/road.sli", "[texture]\nroad.jpg\n"),
+            ("Splines/Roads/texture/road.jpg", "road"),
+            ("Splines/Roads/texture/unused.jpg", "unused"),
+            ("Sceneryobjects/Houses/house.sco", "[mesh]\nhouse.o3d\n"),
+            ("Sceneryobjects/Houses/model/house.o3d", "mesh"),
+            ("Texture/unused.dds", "unused"),
+        ]);
+
+        let result = bundle(serde_json::json!({
+            "assets": [root.join("Splines/Roads/road.sli"), "sceneryobjects/houses"],
+            "omsi_root": root,
+            "output_folder": dir,
+        }));
+        let output_path = result.output_path.unwrap();
+        assert!(output_path.ends_with("road.zip"));
+        let mut names: Vec<String> = archive_names(&output_path).into_iter()
+            .filter(|n| !n.ends_with('/') && !n.ends_with(".json"))
+            .collect();
+        names.sort();
+        assert_eq!(names, vec![
+            "Sceneryobjects/Houses/house.sco",
+            "Sceneryobjects/Houses/model/house.o3d",
+            "Splines/Roads/road.sli",
+            "Splines/Roads/texture/road.jpg",
+        ]);
+
+        let missing = create_bundle(serde_json::from_value(serde_json::json!({
+            "assets": ["Splines\\missing.sli"],
+            "omsi_root": root,
+            "output_folder": dir,
+        })).unwrap(), None);
+        assert!(!missing.success);
+        let unrooted = create_bundle(serde_json::from_value(serde_json::json!({
+            "assets": ["Splines\\Roads\\road.sli"],
+            "output_folder": dir,
+        })).unwrap(), None);
+        assert!(!unrooted.success);
+        let unplaced = create_bundle(serde_json::from_value(serde_json::json!({
+            "assets": ["Splines\\Roads\\road.sli"],
+            "omsi_root": root,
+        })).unwrap(), None);
+        assert!(!unplaced.success);
+        assert!(!root.join("road.zip").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn asset_roots_are_searched_in_order() {
+        let dir = test_dir("bundling_asset_roots");
+        let root = dir.join("OMSI");
+        let (dev, library) = (dir.join("Dev"), dir.join("Library"));
+        write_files(&root, &[
+            ("maps/Grundorf/global.cfg", "[groundtex]\nTexture\\grass.dds\n\n[groundtex]\nTexture\\road.dds\n\n[groundtex]\nTexture\\kerb.dds\n"),
+            ("Texture/grass.dds", "omsi grass"),
+        ]);
+        write_files(&dev, &[("Texture/grass.dds", "dev grass"), ("Texture/road.dds", "dev road")]);
+        write_files(&library, &[("Texture/road.dds", "library road"), ("texture/KERB.dds", "library kerb")]);
+
+        let result = bundle(serde_json::json!({
+            "map_folder": root.join("maps/Grundorf"),
+            "asset_roots": [dev, library],
+            "output_folder": dir,
+        }));
+        let mut archive = zip::ZipArchive::new(fs::File::open(result.output_path.unwrap()).unwrap()).unwrap();
+        let mut read = |name: &str| std::io::read_to_string(archive.by_name(name).unwrap()).unwrap();
+        assert_eq!(read("Texture/grass.dds"), "omsi grass");
+        assert_eq!(read("Texture/road.dds"), "dev road");
+        assert_eq!(read("texture/KERB.dds"), "library kerb");
+
+        let manifest: crate::manifest::SourcesManifest = serde_json::from_str(&read("Grundorf.sources.json")).unwrap();
+        let roots: Vec<(PathBuf, &[String])> = manifest.roots.iter().map(|r| (PathBuf::from(&r.root), r.files.as_slice())).collect();
+        assert_eq!(roots, vec![
+            (root.clone(), &["Texture\\grass.dds".to_string()][..]),
+            (dev.clone(), &["Texture\\road.dds".to_string()][..]),
+            (library.clone(), &["texture\\KERB.dds".to_string()][..]),
+        ]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn normalizes_mount_paths() {
+        assert_eq!(mount_path("Sceneryobjects/./MyObjects/").as_deref(), Some("Sceneryobjects\\MyObjects"));
+        assert_eq!(mount_path("Sceneryobjects\\..\\..\\Windows"), None);
+        assert_eq!(mount_path("C:\\OMSI\\Sceneryobjects"), None);
+        assert_eq!(mount_path("\\Sceneryobjects"), None);
+        assert_eq!(mount_path("."), None);
+    }
+
+    #[test]
+    fn addon_folders_are_mounted_or_reported() {
+        let dir = test_dir("bundling_addons");
+        let root = dir.join("OMSI");
+        let (objects, unmounted) = (dir.join("Work/Objects"), dir.join("Work/Unmounted"));
+        write_files(&root, &[
+            ("maps/Grundorf/global.cfg", ""),
+            ("Vehicles/MyBus/bus.bus", "bus"),
+        ]);
+        write_files(&dir, &[
+            ("Work/Objects/house.sco", "house"),
+            ("Work/Objects/model/house.o3d", "mesh"),
+            ("Work/Unmounted/tree.sco", "tree"),
+        ]);
+
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let recorded = messages.clone();
+        let progress: ProgressCallback = Box::new(move |message, _| recorded.lock().unwrap().push(message.to_string()));
+        let request = serde_json::json!({
+            "map_folder": root.join("maps/Grundorf"),
+            "addon_folder": root.join("Vehicles/MyBus"),
+            "addon_folders": [
+                { "path": objects, "mount": "Sceneryobjects/MyObjects" },
+                { "path": unmounted },
+                { "path": objects, "mount": "..\\Objects" },
+            ],
+            "output_folder": dir,
+        });
+        let result = create_bundle(serde_json::from_value(request).unwrap(), Some(Arc::new(progress)));
+        assert!(result.success, "{:?}", result.error);
+
+        let names = archive_names(result.output_path.as_deref().unwrap());
+        for name in ["Vehicles/MyBus/bus.bus", "Sceneryobjects/MyObjects/house.sco", "Sceneryobjects/MyObjects/model/house.o3d"] {
+            assert!(names.contains(&name.to_string()), "{} missing from {:?}", name, names);
+        }
+        assert!(!names.iter().any(|n| n.contains("tree.sco") || n.contains("Work/")));
+
+        let messages = messages.lock().unwrap();
+        let ignored: Vec<&String> = messages.iter().filter(|m| m.starts_with("Addon folder")).collect();
+        assert_eq!(ignored.len(), 2, "{:?}", ignored);
+        assert!(ignored[0].contains("Unmounted") && ignored[0].ends_with("outside the OMSI folder and has no mount path"));
+        assert!(ignored[1].ends_with("invalid mount path ..\\Objects"));
+
+        let _ = fs::remove_dir_all(&dir);
     }
 }
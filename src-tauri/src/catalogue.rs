@@ -0,0 +1,150 @@
+use std::fs;
+use std::iter::Peekable;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use rayon::prelude::*;
+use walkdir::WalkDir;
+use crate::omsi_root::list_map_folders;
+use crate::phase1_collection::read_global_cfg;
+use crate::types::MapInfo;
+
+/// Picture OMSI shows in the map selection, looked up in the map folder
+const PREVIEW_NAMES: &[&str] = &["picture.jpg", "picture.png", "picture.bmp"];
+
+/// Metadata read from a global.cfg
+#[derive(Debug, Default, PartialEq)]
+pub struct GlobalCfg {
+    pub name: Option<String>,
+    pub friendly_name: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub tiles: usize,
+    pub entry_points: Vec<String>,
+}
+
+/// Value line of a section, unless the next section starts right away
+fn next_value<'a>(lines: &mut Peekable<impl Iterator<Item = &'a str>>) -> Option<String> {
+    lines.next_if(|l| !l.starts_with('['))
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+}
+
+/// Parse the descriptive sections of a global.cfg.
+/// Every `[map]` section is one tile; entry points are numeric lines (position,
+/// rotation, object and tile references) each followed by the name of the entry point.
+pub fn parse_global_cfg(content: &str) -> GlobalCfg {
+    let mut cfg = GlobalCfg::default();
+    let mut lines = content.lines().map(str::trim).peekable();
+
+    while let Some(line) = lines.next() {
+        match line.to_lowercase().as_str() {
+            "[name]" => cfg.name = next_value(&mut lines),
+            "[friendlyname]" => cfg.friendly_name = next_value(&mut lines),
+            "[version]" => cfg.version = next_value(&mut lines),
+            "[author]" => cfg.author = next_value(&mut lines),
+            "[description]" => {
+                let mut text = Vec::new();
+                for l in lines.by_ref() {
+                    if l.eq_ignore_ascii_case("[end]") {
+                        break;
+                    }
+                    text.push(l);
+                }
+                let text = text.join("\n").trim().to_string();
+                cfg.description = (!text.is_empty()).then_some(text);
+            }
+            "[map]" => cfg.tiles += 1,
+            "[entrypoints]" => {
+                let count: usize = next_value(&mut lines).and_then(|c| c.parse().ok()).unwrap_or(0);
+                while cfg.entry_points.len() < count {
+                    match lines.next_if(|l| !l.starts_with('[')) {
+                        Some(l) if l.is_empty() || l.parse::<f64>().is_ok() => {}
+                        Some(name) => cfg.entry_points.push(name.to_string()),
+                        None => break,
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    cfg
+}
+
+/// Newest modification time of the files in a map folder (Unix seconds)
+fn last_modified(map_folder: &Path) -> u64 {
+    WalkDir::new(map_folder)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok()?.modified().ok())
+        .filter_map(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .max()
+        .unwrap_or(0)
+}
+
+fn preview(map_folder: &Path) -> Option<String> {
+    fs::read_dir(map_folder).ok()?
+        .filter_map(|e| e.ok())
+        .find(|e| {
+            let name = e.file_name().to_string_lossy().to_lowercase();
+            PREVIEW_NAMES.contains(&name.as_str()) && e.path().is_file()
+        })
+        .map(|e| e.path().to_string_lossy().to_string())
+}
+
+/// Catalogue entry for one map folder
+pub fn map_info(maps_dir: &Path, map_folder: &Path) -> MapInfo {
+    let folder = map_folder.strip_prefix(maps_dir).unwrap_or(map_folder).to_string_lossy().replace('/', "\\");
+    let (cfg, error) = match read_global_cfg(map_folder, None) {
+        Ok(content) => (parse_global_cfg(&content), None),
+        Err(e) => (GlobalCfg::default(), Some(format!("Failed to read global.cfg: {}", e))),
+    };
+
+    MapInfo {
+        name: cfg.friendly_name.or(cfg.name).unwrap_or_else(|| folder.clone()),
+        folder,
+        path: map_folder.to_string_lossy().to_string(),
+        description: cfg.description,
+        version: cfg.version,
+        author: cfg.author,
+        tiles: cfg.tiles,
+        entry_points: cfg.entry_points,
+        preview: preview(map_folder),
+        modified: last_modified(map_folder),
+        error,
+    }
+}
+
+/// All maps of an OMSI root, sorted by folder
+pub fn list_maps(omsi_root: String) -> Vec<MapInfo> {
+    let root = Path::new(&omsi_root);
+    let maps_dir = root.join("maps");
+    list_map_folders(root)
+        .par_iter()
+        .map(|folder| map_info(&maps_dir, folder))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_map_metadata() {
+        let content = "[name]\r\nGrundorf\r\n\r\n[friendlyname]\r\nGrundorf (Demo)\r\n\r\n\
+            [description]\r\nA small town.\r\nSecond line.\r\n[end]\r\n\r\n[version]\r\n14\r\n\r\n\
+            [map]\r\n0\r\n0\r\ntile_0_0.map\r\n\r\n[map]\r\n1\r\n0\r\ntile_1_0.map\r\n\r\n\
+            [entrypoints]\r\n2\r\n0\r\n2170\r\n0\r\n-339.46\r\n0\r\n-219.6\r\n0\r\n0\r\n0.7071\r\n0.7071\r\n1\r\nDepot\r\n\
+            1\r\n2304\r\n0\r\n12.5\r\n0\r\n88\r\n0\r\n0\r\n0\r\n1\r\n0\r\nMarktplatz\r\n";
+        let cfg = parse_global_cfg(content);
+        assert_eq!(cfg.name.as_deref(), Some("Grundorf"));
+        assert_eq!(cfg.friendly_name.as_deref(), Some("Grundorf (Demo)"));
+        assert_eq!(cfg.description.as_deref(), Some("A small town.\nSecond line."));
+        assert_eq!(cfg.version.as_deref(), Some("14"));
+        assert_eq!(cfg.author, None);
+        assert_eq!(cfg.tiles, 2);
+        assert_eq!(cfg.entry_points, vec!["Depot", "Marktplatz"]);
+    }
+}
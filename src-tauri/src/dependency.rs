@@ -0,0 +1,70 @@
+use crate::types::{Dependency, DependencyCategory};
+
+impl Dependency {
+    /// Path relative to the OMSI root
+    pub fn path(&self) -> &str {
+        match self {
+            Dependency::File(path)
+            | Dependency::Folder(path)
+            | Dependency::Optional(path)
+            | Dependency::Missing(path)
+            | Dependency::Excluded(path) => path,
+        }
+    }
+
+    pub fn is_folder(&self) -> bool {
+        matches!(self, Dependency::Folder(_))
+    }
+
+    pub fn category(&self) -> DependencyCategory {
+        DependencyCategory::of(self.path())
+    }
+}
+
+impl DependencyCategory {
+    /// Category of a path relative to the OMSI root
+    pub fn of(path: &str) -> DependencyCategory {
+        let top = path.split(['\\', '/']).next().unwrap_or("").to_lowercase();
+        match top.as_str() {
+            "sceneryobjects" => DependencyCategory::Sceneryobjects,
+            "splines" => DependencyCategory::Splines,
+            "texture" => DependencyCategory::Textures,
+            "vehicles" => DependencyCategory::Vehicles,
+            "humans" => DependencyCategory::Humans,
+            "trains" => DependencyCategory::Trains,
+            "sound" | "sounds" => DependencyCategory::Sounds,
+            "script" | "scripts" => DependencyCategory::Scripts,
+            "money" => DependencyCategory::MoneySystems,
+            "ticketpacks" => DependencyCategory::TicketPacks,
+            "maps" => DependencyCategory::Maps,
+            _ => DependencyCategory::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DependencyEntry, DependencySource};
+
+    #[test]
+    fn serializes_kind_path_and_metadata() {
+        let dependency = Dependency::Folder("Vehicles\\Bus1".to_string());
+        let entry = DependencyEntry {
+            category: dependency.category(),
+            dependency,
+            source: DependencySource::Section,
+        };
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "kind": "folder",
+            "path": "Vehicles\\Bus1",
+            "category": "vehicles",
+            "source": "section",
+        }));
+
+        let parsed: DependencyEntry = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.dependency, Dependency::Folder("Vehicles\\Bus1".to_string()));
+        assert_eq!(DependencyCategory::of("Texture\\a.jpg"), DependencyCategory::Textures);
+    }
+}
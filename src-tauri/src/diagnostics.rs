@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::types::{ScanReport, ScanReportGroup};
+use crate::utils::app_log_dir;
+
+/// Log file name inside the application log directory
+const LOG_FILE_NAME: &str = "omsimapbundler.log";
+
+/// Log files larger than this are rotated to `.old` on startup
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Verbosity of the diagnostics log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error = 0,
+    Warn = 1,
+    Info = 2,
+    Debug = 3,
+}
+
+impl LogLevel {
+    /// Parse a level name as sent by the frontend ("error", "warn", "info", "debug")
+    pub fn parse(name: &str) -> Option<LogLevel> {
+        match name.to_lowercase().as_str() {
+            "error" => Some(LogLevel::Error),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+        }
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Open the log file in the application log directory.
+/// Logging to stdout/stderr works without calling this.
+pub fn init(level: LogLevel) {
+    set_level(level);
+
+    let path = log_file_path();
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if fs::metadata(&path).map(|m| m.len() > MAX_LOG_SIZE).unwrap_or(false) {
+        let _ = fs::rename(&path, path.with_extension("log.old"));
+    }
+
+    match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(file) => *LOG_FILE.lock().unwrap() = Some(file),
+        Err(e) => eprintln!("Failed to open log file {:?}: {}", path, e),
+    }
+}
+
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Log at `level` until the returned guard is dropped, then go back to the previous level
+pub fn scoped_level(level: LogLevel) -> LevelGuard {
+    LevelGuard { previous: LEVEL.swap(level as u8, Ordering::Relaxed) }
+}
+
+/// Restores the previous log level when dropped (see `scoped_level`)
+pub struct LevelGuard {
+    previous: u8,
+}
+
+impl Drop for LevelGuard {
+    fn drop(&mut self) {
+        LEVEL.store(self.previous, Ordering::Relaxed);
+    }
+}
+
+pub fn enabled(level: LogLevel) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Location of the current log file
+pub fn log_file_path() -> PathBuf {
+    app_log_dir().join(LOG_FILE_NAME)
+}
+
+/// Write a message to the console and the log file. Use the `log_*!` macros instead of calling this directly.
+pub fn log(level: LogLevel, args: std::fmt::Arguments) {
+    if !enabled(level) {
+        return;
+    }
+
+    let message = args.to_string();
+    if level <= LogLevel::Warn {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+
+    if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let _ = writeln!(file, "{} [{}] {}", timestamp, level.label(), message);
+    }
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => { $crate::diagnostics::log($crate::diagnostics::LogLevel::Error, format_args!($($arg)*)) };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => { $crate::diagnostics::log($crate::diagnostics::LogLevel::Warn, format_args!($($arg)*)) };
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => { $crate::diagnostics::log($crate::diagnostics::LogLevel::Info, format_args!($($arg)*)) };
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => { $crate::diagnostics::log($crate::diagnostics::LogLevel::Debug, format_args!($($arg)*)) };
+}
+
+// ============================================================================
+// SCAN REPORT
+// ============================================================================
+
+/// Build a report of the paths collected in phase 1, grouped by extension
+pub fn build_scan_report<'a>(paths: impl IntoIterator<Item = &'a str>, map_folder: &Path) -> ScanReport {
+    let mut grouped: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut total = 0;
+    for path in paths {
+        let ext = path.rsplit('.').next().unwrap_or("unknown").to_lowercase();
+        grouped.entry(ext).or_default().push(path.to_string());
+        total += 1;
+    }
+
+    ScanReport {
+        map_folder: map_folder.to_string_lossy().to_string(),
+        total,
+        groups: grouped.into_iter()
+            .map(|(extension, mut paths)| {
+                paths.sort();
+                ScanReportGroup { extension, paths }
+            })
+            .collect(),
+    }
+}
+
+/// Render a scan report as human-readable text
+pub fn format_scan_report(report: &ScanReport) -> String {
+    let mut content = String::new();
+    content.push_str(&format!("=== PHASE 1: Collected {} file paths ===\n", report.total));
+    content.push_str(&format!("Map folder: {}\n\n", report.map_folder));
+
+    // Add summary at the top
+    content.push_str("Summary by extension:\n");
+    for group in &report.groups {
+        content.push_str(&format!("  .{}: {}\n", group.extension, group.paths.len()));
+    }
+    content.push('\n');
+
+    // Detailed listing grouped by extension
+    for group in &report.groups {
+        content.push_str(&format!("\n.{} files ({}):\n", group.extension, group.paths.len()));
+        content.push_str(&"─".repeat(60));
+        content.push('\n');
+        for path in &group.paths {
+            content.push_str(&format!("  {}\n", path));
+        }
+    }
+
+    content.push_str(&format!("\n{}\n", "=".repeat(60)));
+    content.push_str(&format!("Total: {} paths collected\n", report.total));
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoped_level_is_restored() {
+        set_level(LogLevel::Info);
+        {
+            let _level = scoped_level(LogLevel::Debug);
+            assert!(enabled(LogLevel::Debug));
+        }
+        assert!(!enabled(LogLevel::Debug));
+        assert!(enabled(LogLevel::Info));
+    }
+}
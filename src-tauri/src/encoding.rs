@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1250, WINDOWS_1252};
+use crate::file_index::{fnv1a, normalize_key, FNV_OFFSET};
+
+/// Code page selection for a legacy (non-Unicode) text file. `None` means auto-detect.
+pub type CodePage = Option<&'static Encoding>;
+
+/// Code pages used for one bundle run: a default plus per-folder overrides
+#[derive(Debug, Clone, Default)]
+pub struct CodePages {
+    default: CodePage,
+    /// (normalized folder key relative to the OMSI root, code page), longest key first
+    folders: Vec<(String, &'static Encoding)>,
+}
+
+impl CodePages {
+    /// Build the settings from the request values.
+    /// Folder keys may be absolute or relative to the OMSI root.
+    pub fn new(
+        omsi_root: &Path,
+        default: Option<&str>,
+        folders: Option<&HashMap<String, String>>,
+    ) -> Result<CodePages, String> {
+        let default = match default {
+            Some(name) => parse_code_page(name)?,
+            None => None,
+        };
+
+        let root_key = normalize_key(&omsi_root.to_string_lossy());
+        let mut folder_pages = Vec::new();
+        for (folder, name) in folders.into_iter().flatten() {
+            let key = normalize_key(folder);
+            // Only whole components: "C:\OMSI 2 Dev" is not below "C:\OMSI 2"
+            let key = match key.strip_prefix(&root_key) {
+                Some(rest) if !root_key.is_empty() && (rest.is_empty() || rest.starts_with('\\')) => {
+                    rest.trim_start_matches('\\').to_string()
+                }
+                _ => key,
+            };
+            // "auto" on a folder simply falls through to the default
+            if let Some(encoding) = parse_code_page(name)? {
+                folder_pages.push((key, encoding));
+            }
+        }
+        folder_pages.sort_by_key(|(key, _)| std::cmp::Reverse(key.len()));
+
+        Ok(CodePages { default, folders: folder_pages })
+    }
+
+    /// Code page for a file, given its path relative to the OMSI root
+    pub fn for_path(&self, rel: &str) -> CodePage {
+        let key = normalize_key(rel);
+        self.folders.iter()
+            .find(|(folder, _)| {
+                key.strip_prefix(folder.as_str())
+                    .map(|rest| rest.is_empty() || rest.starts_with('\\'))
+                    .unwrap_or(false)
+            })
+            .map(|(_, encoding)| Some(*encoding))
+            .unwrap_or(self.default)
+    }
+}
+
+/// Parse a code page name: "auto", a number such as "1250", or any WHATWG label ("windows-1250", "cp1250", ...)
+pub fn parse_code_page(name: &str) -> Result<CodePage, String> {
+    let name = name.trim();
+    if name.is_empty() || name.eq_ignore_ascii_case("auto") {
+        return Ok(None);
+    }
+    let label = if name.chars().all(|c| c.is_ascii_digit()) {
+        format!("windows-{}", name)
+    } else {
+        name.to_string()
+    };
+    Encoding::for_label(label.as_bytes())
+        .map(Some)
+        .ok_or_else(|| format!("Unknown code page: {}", name))
+}
+
+/// Fingerprint of a code page selection, mixed into parse cache stamps
+pub fn code_page_stamp(code_page: CodePage) -> u64 {
+    fnv1a(FNV_OFFSET, code_page.map(|e| e.name()).unwrap_or("auto").as_bytes())
+}
+
+/// Read a text file and decode it (see `decode_text`)
+pub fn read_text_file(path: &Path, code_page: CodePage) -> std::io::Result<String> {
+    let bytes = fs::read(path)?;
+    Ok(decode_text(&bytes, code_page))
+}
+
+/// Decode the contents of an OMSI text file.
+///
+/// Order: byte order mark, BOM-less UTF-16LE, valid UTF-8, then the given legacy
+/// code page or, when none is given, a Windows-1250/1252 guess.
+pub fn decode_text(bytes: &[u8], code_page: CodePage) -> String {
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return UTF_16LE.decode_without_bom_handling(rest).0.into_owned();
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return UTF_16BE.decode_without_bom_handling(rest).0.into_owned();
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return UTF_8.decode_without_bom_handling(rest).0.into_owned();
+    }
+    if looks_like_utf16le(bytes) {
+        return UTF_16LE.decode_without_bom_handling(bytes).0.into_owned();
+    }
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        return s.to_string();
+    }
+
+    let encoding = code_page.unwrap_or_else(|| detect_legacy_code_page(bytes));
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// Guess between Windows-1250 (Central European) and Windows-1252 (Western European).
+///
+/// Bytes that are letters in 1250 but symbols or unassigned in 1252 (ą, ł, ľ, ť, ż, ...) count
+/// strongly towards 1250; common Czech/Polish letters (č, ř, ě, ů, ś, ...) count weakly.
+/// German umlauts and ß share their code points, so they don't matter. Ties go to 1252.
+pub fn detect_legacy_code_page(bytes: &[u8]) -> &'static Encoding {
+    let mut score: i32 = 0;
+    for &b in bytes {
+        score += match b {
+            // Letters in 1250; unassigned or symbols in 1252
+            0x8D | 0x8F | 0x9D | 0xA3 | 0xA5 | 0xAF | 0xB3 | 0xB9 | 0xBC | 0xBE | 0xBF => 3,
+            // Common Czech/Slovak/Polish letters; rarer letters in 1252
+            0x8C | 0x9C | 0x9F | 0xC8 | 0xCC | 0xD8 | 0xD9 | 0xE8 | 0xEC | 0xEF | 0xF2 | 0xF8 | 0xF9 => 1,
+            // Letters in 1252 only (ƒ, ˆ, ˜ are unassigned in 1250)
+            0x83 | 0x88 | 0x98 => -3,
+            // Western letters that map to rare letters in 1250 (à, ã, õ, û, ...)
+            0xC0 | 0xC3 | 0xD5 | 0xDB | 0xE0 | 0xE3 | 0xF5 | 0xFB => -1,
+            _ => 0,
+        };
+    }
+
+    if score > 0 {
+        WINDOWS_1250
+    } else {
+        WINDOWS_1252
+    }
+}
+
+/// UTF-16LE text without a BOM has a zero high byte for nearly every ASCII character
+fn looks_like_utf16le(bytes: &[u8]) -> bool {
+    if bytes.len() < 4 || !bytes.len().is_multiple_of(2) {
+        return false;
+    }
+    let pairs = bytes.len() / 2;
+    let zero_high = bytes.chunks_exact(2).filter(|p| p[1] == 0 && p[0] != 0).count();
+    let zero_low = bytes.chunks_exact(2).filter(|p| p[0] == 0).count();
+    zero_high * 10 >= pairs * 7 && zero_low * 10 < pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_central_european_names() {
+        // "Sceneryobjects\Příměstská zastávka\ulice.sco" in Windows-1250
+        let (bytes, _, _) = WINDOWS_1250.encode("Sceneryobjects\\Příměstská zastávka\\ulice.sco");
+        assert_eq!(detect_legacy_code_page(&bytes), WINDOWS_1250);
+        assert_eq!(decode_text(&bytes, None), "Sceneryobjects\\Příměstská zastávka\\ulice.sco");
+    }
+
+    #[test]
+    fn keeps_western_names_and_overrides() {
+        let (bytes, _, _) = WINDOWS_1252.encode("Texture\\Straße_Façade_à.bmp");
+        assert_eq!(decode_text(&bytes, None), "Texture\\Straße_Façade_à.bmp");
+
+        let (bytes, _, _) = WINDOWS_1250.encode("Texture\\dlažba.bmp");
+        assert_eq!(decode_text(&bytes, Some(WINDOWS_1250)), "Texture\\dlažba.bmp");
+    }
+
+    #[test]
+    fn decodes_utf16_with_and_without_bom() {
+        let utf16: Vec<u8> = "[object]\r\n".encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        assert_eq!(decode_text(&utf16, None), "[object]\r\n");
+
+        let mut with_bom = vec![0xFF, 0xFE];
+        with_bom.extend(&utf16);
+        assert_eq!(decode_text(&with_bom, None), "[object]\r\n");
+    }
+
+    #[test]
+    fn folder_overrides_use_longest_match() {
+        let mut folders = HashMap::new();
+        folders.insert("Sceneryobjects".to_string(), "1252".to_string());
+        folders.insert("C:\\OMSI 2\\Sceneryobjects\\CZ".to_string(), "windows-1250".to_string());
+        let pages = CodePages::new(Path::new("C:\\OMSI 2"), Some("auto"), Some(&folders)).unwrap();
+
+        assert_eq!(pages.for_path("sceneryobjects\\cz\\a.sco"), Some(WINDOWS_1250));
+        assert_eq!(pages.for_path("Sceneryobjects\\CZX\\a.sco"), Some(WINDOWS_1252));
+        assert_eq!(pages.for_path("Splines\\a.sli"), None);
+        assert!(parse_code_page("klingon").is_err());
+    }
+
+    #[test]
+    fn folder_overrides_are_only_relative_to_the_root_at_a_component_boundary() {
+        let mut folders = HashMap::new();
+        folders.insert("C:\\OMSI 2 Dev\\Sceneryobjects".to_string(), "1250".to_string());
+        folders.insert("C:\\OMSI 2\\Splines".to_string(), "1252".to_string());
+        let pages = CodePages::new(Path::new("C:\\OMSI 2"), None, Some(&folders)).unwrap();
+
+        assert_eq!(pages.for_path("Splines\\a.sli"), Some(WINDOWS_1252));
+        // A sibling folder whose name starts like the root stays absolute and matches nothing
+        assert_eq!(pages.for_path("Sceneryobjects\\a.sco"), None);
+        assert_eq!(pages.for_path(" dev\\Sceneryobjects\\a.sco"), None);
+    }
+}
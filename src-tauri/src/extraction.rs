@@ -1,6 +1,7 @@
 use std::path::Path;
-use crate::types::DependencyResult;
+use crate::types::{DependencyCategory, DependencyResult};
 use crate::phase1_collection;
+use crate::parse_cache::ParseCache;
 
 /// Extract all dependencies from map folder
 /// 
@@ -10,7 +11,12 @@ pub fn extract_dependencies(map_folder: String) -> DependencyResult {
     let path = Path::new(&map_folder);
 
     // Phase 1: Collect ALL file paths without categorization
-    let _all_paths = match phase1_collection::collect_all_dependencies(path) {
+    let cache = ParseCache::load_default();
+    let all_paths = phase1_collection::collect_all_dependencies(path, &cache, None);
+    if let Err(e) = cache.save() {
+        log_warn!("Failed to save parse cache: {}", e);
+    }
+    let all_paths = match all_paths {
         Ok(paths) => paths,
         Err(e) => {
             let error_msg = format!("Failed to collect dependencies: {}", e);
@@ -28,7 +34,7 @@ pub fn extract_dependencies(map_folder: String) -> DependencyResult {
         }
     };
 
-    DependencyResult {
+    let mut result = DependencyResult {
         sceneryobjects: Vec::new(),
         splines: Vec::new(),
         textures: Vec::new(),
@@ -38,5 +44,23 @@ pub fn extract_dependencies(map_folder: String) -> DependencyResult {
         ticket_packs: Vec::new(),
         tile_maps: Vec::new(),
         error: None,
+    };
+
+    // Sort the files into lists by the category of their top-level folder
+    let mut dependencies: Vec<_> = all_paths.into_dependencies().into_iter().collect();
+    dependencies.sort();
+    for dependency in dependencies {
+        let list = match dependency.category() {
+            DependencyCategory::Sceneryobjects => &mut result.sceneryobjects,
+            DependencyCategory::Splines => &mut result.splines,
+            DependencyCategory::Textures => &mut result.textures,
+            DependencyCategory::Humans => &mut result.humans,
+            DependencyCategory::Vehicles => &mut result.vehicles,
+            DependencyCategory::MoneySystems => &mut result.money_systems,
+            DependencyCategory::TicketPacks => &mut result.ticket_packs,
+            _ => continue,
+        };
+        list.push(dependency.path().to_string());
     }
+    result
 }
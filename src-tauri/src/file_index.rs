@@ -0,0 +1,474 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use rayon::prelude::*;
+use walkdir::WalkDir;
+
+/// Top-level OMSI folders that are indexed up front.
+/// Anything outside these subtrees falls back to direct filesystem queries.
+const INDEXED_ROOTS: &[&str] = &[
+    "sceneryobjects",
+    "splines",
+    "texture",
+    "vehicles",
+    "humans",
+    "sound",
+    "sounds",
+    "script",
+    "scripts",
+    "trains",
+    "money",
+    "ticketpacks",
+];
+
+/// A single file or directory known to the index
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    /// Real path relative to the OMSI root, with backslash separators
+    pub path: String,
+    /// Real file name (last path component)
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Modification time in seconds since the UNIX epoch
+    pub modified: u64,
+}
+
+/// In-memory index of the relevant OMSI root subtrees.
+///
+/// Built once per run so that extractors can do case-insensitive lookups
+/// without hitting `read_dir`/`exists()` for every object.
+/// Keys are lowercased, backslash-separated paths relative to the OMSI root.
+///
+/// Fallback roots (secondary asset libraries) are searched in order for
+/// paths that do not exist in the main root; directory listings merge all roots.
+pub struct FileIndex {
+    root: PathBuf,
+    entries: HashMap<String, FileEntry>,
+    children: HashMap<String, Vec<String>>,
+    stamps: Mutex<HashMap<String, u64>>,
+    fallbacks: Vec<FileIndex>,
+}
+
+impl FileIndex {
+    /// Walk the indexed subtrees of `omsi_root` and build the index
+    pub fn build(omsi_root: &Path) -> FileIndex {
+        let mut top_level = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(omsi_root) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if entry.path().is_dir() && INDEXED_ROOTS.contains(&name.to_lowercase().as_str()) {
+                    top_level.push(entry.path());
+                }
+            }
+        }
+
+        // Walk every top-level folder in parallel
+        let walked: Vec<Vec<FileEntry>> = top_level.par_iter()
+            .map(|dir| {
+                WalkDir::new(dir)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter_map(|e| {
+                        let rel = e.path().strip_prefix(omsi_root).ok()?;
+                        let metadata = e.metadata().ok()?;
+                        let modified = metadata.modified().ok()
+                            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                            .map(|d| d.as_secs())
+                            .unwrap_or(0);
+                        Some(FileEntry {
+                            path: rel.to_string_lossy().replace('/', "\\"),
+                            name: e.file_name().to_string_lossy().to_string(),
+                            is_dir: e.file_type().is_dir(),
+                            size: if metadata.is_file() { metadata.len() } else { 0 },
+                            modified,
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut index = FileIndex {
+            root: omsi_root.to_path_buf(),
+            entries: HashMap::new(),
+            children: HashMap::new(),
+            stamps: Mutex::new(HashMap::new()),
+            fallbacks: Vec::new(),
+        };
+
+        for entry in walked.into_iter().flatten() {
+            let key = normalize_key(&entry.path);
+            if index.entries.contains_key(&key) {
+                // Case-only duplicates can exist on case-sensitive filesystems, first one wins
+                continue;
+            }
+            let parent = match key.rfind('\\') {
+                Some(pos) => key[..pos].to_string(),
+                None => String::new(),
+            };
+            index.children.entry(parent).or_default().push(key.clone());
+            index.entries.insert(key, entry);
+        }
+
+        let total_size: u64 = index.entries.values().map(|e| e.size).sum();
+        log_info!("[INDEX] Indexed {} entries ({} MB) under {:?}", index.entries.len(), total_size / (1024 * 1024), omsi_root);
+        index
+    }
+
+    /// Build the index of `omsi_root` plus one for every fallback root, searched in order
+    pub fn build_with_fallbacks(omsi_root: &Path, fallback_roots: &[PathBuf]) -> FileIndex {
+        let mut index = FileIndex::build(omsi_root);
+        index.fallbacks = fallback_roots.iter().map(|root| FileIndex::build(root)).collect();
+        index
+    }
+
+    /// Index (main or fallback) that holds `rel`; the main index when no root has it
+    fn owner(&self, rel: &str) -> &FileIndex {
+        if self.fallbacks.is_empty() || self.local_exists(rel) {
+            return self;
+        }
+        self.fallbacks.iter().find(|f| f.local_exists(rel)).unwrap_or(self)
+    }
+
+    /// Root folder a relative path is served from
+    pub fn root_of(&self, rel: &str) -> &Path {
+        &self.owner(rel).root
+    }
+
+    /// Absolute path for a relative path, using the real on-disk casing when known
+    pub fn full_path(&self, rel: &str) -> PathBuf {
+        let owner = self.owner(rel);
+        match owner.local_real_path(rel) {
+            Some(real) => owner.join(&real),
+            None => self.join(rel),
+        }
+    }
+
+    /// Join an OMSI-style relative path (backslash separators) onto the root
+    fn join(&self, rel: &str) -> PathBuf {
+        self.root.join(rel.replace('\\', "/"))
+    }
+
+    /// Real relative path of an existing file or directory
+    pub fn real_path(&self, rel: &str) -> Option<String> {
+        self.owner(rel).local_real_path(rel)
+    }
+
+    pub fn is_file(&self, rel: &str) -> bool {
+        self.owner(rel).local_is_file(rel)
+    }
+
+    pub fn is_dir(&self, rel: &str) -> bool {
+        self.owner(rel).local_is_dir(rel)
+    }
+
+    fn local_real_path(&self, rel: &str) -> Option<String> {
+        let key = normalize_key(rel);
+        if is_indexed(&key) {
+            return self.entries.get(&key).map(|e| e.path.clone());
+        }
+        if self.join(rel).exists() {
+            Some(rel.replace('/', "\\"))
+        } else {
+            None
+        }
+    }
+
+    fn local_exists(&self, rel: &str) -> bool {
+        let key = normalize_key(rel);
+        if is_indexed(&key) {
+            return self.entries.contains_key(&key);
+        }
+        self.join(rel).exists()
+    }
+
+    fn local_is_file(&self, rel: &str) -> bool {
+        let key = normalize_key(rel);
+        if is_indexed(&key) {
+            return self.entries.get(&key).map(|e| !e.is_dir).unwrap_or(false);
+        }
+        self.join(rel).is_file()
+    }
+
+    fn local_is_dir(&self, rel: &str) -> bool {
+        let key = normalize_key(rel);
+        if is_indexed(&key) {
+            return self.entries.get(&key).map(|e| e.is_dir).unwrap_or(false);
+        }
+        self.join(rel).is_dir()
+    }
+
+    /// Find a file inside a directory case-insensitively.
+    /// Returns the real relative path.
+    pub fn find_file(&self, dir: &Path, filename: &str) -> Option<String> {
+        let rel = dir.join(filename).to_string_lossy().to_string();
+        if self.is_file(&rel) {
+            self.real_path(&rel)
+        } else {
+            None
+        }
+    }
+
+    /// Real relative paths of the direct subdirectories of `dir`
+    pub fn subdirs(&self, dir: &Path) -> Vec<String> {
+        self.list(dir, |e| e.is_dir)
+    }
+
+    /// Real relative paths of the files directly inside `dir`
+    pub fn files_in(&self, dir: &Path) -> Vec<String> {
+        self.list(dir, |e| !e.is_dir)
+    }
+
+    /// Real relative paths of files directly inside `dir` whose name starts with `prefix` (case-insensitive)
+    pub fn files_with_prefix(&self, dir: &Path, prefix: &str) -> Vec<String> {
+        let prefix_lower = prefix.to_lowercase();
+        self.list(dir, |e| !e.is_dir && e.name.to_lowercase().starts_with(&prefix_lower))
+    }
+
+    /// Real relative paths of all files below `dir`, recursively
+    pub fn files_under(&self, dir: &Path) -> Vec<String> {
+        self.merged(|index| index.local_files_under(dir))
+    }
+
+    /// Results of `query` on the main index and every fallback, the first root winning for each path
+    fn merged(&self, query: impl Fn(&FileIndex) -> Vec<String>) -> Vec<String> {
+        let mut result = query(self);
+        if self.fallbacks.is_empty() {
+            return result;
+        }
+        let mut seen: HashSet<String> = result.iter().map(|p| normalize_key(p)).collect();
+        for fallback in &self.fallbacks {
+            result.extend(query(fallback).into_iter().filter(|p| seen.insert(normalize_key(p))));
+        }
+        result
+    }
+
+    fn local_files_under(&self, dir: &Path) -> Vec<String> {
+        let dir_str = dir.to_string_lossy().to_string();
+        let key = normalize_key(&dir_str);
+        if !is_indexed(&key) {
+            let abs = self.join(&dir_str);
+            return WalkDir::new(&abs)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .filter_map(|e| e.path().strip_prefix(&self.root).ok().map(|p| p.to_string_lossy().replace('/', "\\")))
+                .collect();
+        }
+
+        let mut files = Vec::new();
+        let mut stack = vec![key];
+        while let Some(current) = stack.pop() {
+            if let Some(children) = self.children.get(&current) {
+                for child in children {
+                    if let Some(entry) = self.entries.get(child) {
+                        if entry.is_dir {
+                            stack.push(child.clone());
+                        } else {
+                            files.push(entry.path.clone());
+                        }
+                    }
+                }
+            }
+        }
+        files
+    }
+
+    /// Whether `rel` lies in an indexed subtree, where `subtree_stamp` follows changes
+    pub fn tracks(&self, rel: &str) -> bool {
+        is_indexed(&normalize_key(rel))
+    }
+
+    /// Fingerprint of a directory subtree (names, sizes and modification times).
+    /// Changes whenever a file below `dir` is added, removed or modified, in any root.
+    pub fn subtree_stamp(&self, dir: &Path) -> u64 {
+        self.fallbacks.iter().fold(self.local_subtree_stamp(dir), |stamp, fallback| {
+            fnv1a(stamp, &fallback.local_subtree_stamp(dir).to_le_bytes())
+        })
+    }
+
+    fn local_subtree_stamp(&self, dir: &Path) -> u64 {
+        let key = normalize_key(&dir.to_string_lossy());
+        if let Some(stamp) = self.stamps.lock().unwrap().get(&key) {
+            return *stamp;
+        }
+
+        let mut entries: Vec<&FileEntry> = Vec::new();
+        let mut stack = vec![key.clone()];
+        while let Some(current) = stack.pop() {
+            if let Some(children) = self.children.get(&current) {
+                for child in children {
+                    if let Some(entry) = self.entries.get(child) {
+                        if entry.is_dir {
+                            stack.push(child.clone());
+                        }
+                        entries.push(entry);
+                    }
+                }
+            }
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut stamp = FNV_OFFSET;
+        for entry in entries {
+            stamp = fnv1a(stamp, entry.path.as_bytes());
+            stamp = fnv1a(stamp, &entry.size.to_le_bytes());
+            stamp = fnv1a(stamp, &entry.modified.to_le_bytes());
+        }
+
+        self.stamps.lock().unwrap().insert(key, stamp);
+        stamp
+    }
+
+    fn list(&self, dir: &Path, filter: impl Fn(&FileEntry) -> bool) -> Vec<String> {
+        self.merged(|index| index.local_list(dir, &filter))
+    }
+
+    fn local_list(&self, dir: &Path, filter: &impl Fn(&FileEntry) -> bool) -> Vec<String> {
+        let dir_str = dir.to_string_lossy().to_string();
+        let key = normalize_key(&dir_str);
+        if !is_indexed(&key) {
+            // Outside the indexed subtrees: read the directory directly
+            let mut result = Vec::new();
+            if let Ok(entries) = std::fs::read_dir(self.join(&dir_str)) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let fake = FileEntry {
+                        path: String::new(),
+                        name: entry.file_name().to_string_lossy().to_string(),
+                        is_dir: path.is_dir(),
+                        size: 0,
+                        modified: 0,
+                    };
+                    if filter(&fake) {
+                        if let Ok(rel) = path.strip_prefix(&self.root) {
+                            result.push(rel.to_string_lossy().replace('/', "\\"));
+                        }
+                    }
+                }
+            }
+            return result;
+        }
+
+        self.children.get(&key)
+            .map(|children| {
+                children.iter()
+                    .filter_map(|c| self.entries.get(c))
+                    .filter(|e| filter(e))
+                    .map(|e| e.path.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Normalize a relative path into an index key: lowercase, backslash-separated,
+/// with `.` and `..` components resolved lexically
+pub fn normalize_key(rel: &str) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for part in rel.split(['\\', '/']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            other => parts.push(other.to_lowercase()),
+        }
+    }
+    parts.join("\\")
+}
+
+pub const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// FNV-1a hash step, stable across runs (unlike `DefaultHasher`)
+pub fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn is_indexed(key: &str) -> bool {
+    let first = key.split('\\').next().unwrap_or("");
+    INDEXED_ROOTS.contains(&first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::test_utils::test_dir;
+
+    fn test_root(name: &str) -> PathBuf {
+        let root = test_dir(&format!("index_{}", name));
+        fs::create_dir_all(root.join("Sceneryobjects/Author/Model")).unwrap();
+        fs::write(root.join("Sceneryobjects/Author/House.sco"), b"[mesh]").unwrap();
+        fs::write(root.join("Sceneryobjects/Author/House_Night.sco"), b"").unwrap();
+        fs::write(root.join("Sceneryobjects/Author/Model/house.o3d"), b"mesh").unwrap();
+        root
+    }
+
+    #[test]
+    fn looks_up_paths_case_insensitively() {
+        let root = test_root("lookup");
+        let index = FileIndex::build(&root);
+
+        assert!(index.is_file("sceneryobjects\\AUTHOR\\house.SCO"));
+        assert!(index.is_dir("SCENERYOBJECTS/author/model"));
+        assert!(!index.is_file("Sceneryobjects\\Author\\Model"));
+        assert_eq!(index.real_path("sceneryobjects\\author\\model\\..\\house.sco").as_deref(),
+            Some("Sceneryobjects\\Author\\House.sco"));
+        assert_eq!(index.find_file(Path::new("sceneryobjects\\author\\MODEL"), "HOUSE.O3D").as_deref(),
+            Some("Sceneryobjects\\Author\\Model\\house.o3d"));
+        assert_eq!(index.real_path("Sceneryobjects\\Author\\missing.sco"), None);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn lists_files_with_prefix() {
+        let root = test_root("prefix");
+        let index = FileIndex::build(&root);
+
+        let mut found = index.files_with_prefix(Path::new("sceneryobjects\\author"), "HOUSE");
+        found.sort();
+        assert_eq!(found, vec![
+            "Sceneryobjects\\Author\\House.sco".to_string(),
+            "Sceneryobjects\\Author\\House_Night.sco".to_string(),
+        ]);
+        assert!(index.files_with_prefix(Path::new("Sceneryobjects\\Author"), "Model").is_empty());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn subtree_stamp_follows_changes() {
+        let root = test_root("stamp");
+        let dir = Path::new("Sceneryobjects\\Author");
+        let stamp = |root: &Path| FileIndex::build(root).subtree_stamp(dir);
+
+        let original = stamp(&root);
+        assert_eq!(stamp(&root), original);
+        assert_eq!(FileIndex::build(&root).subtree_stamp(Path::new("sceneryobjects\\AUTHOR")), original);
+
+        fs::write(root.join("Sceneryobjects/Author/Model/house.o3d"), b"longer mesh").unwrap();
+        let resized = stamp(&root);
+        assert_ne!(resized, original);
+
+        fs::write(root.join("Sceneryobjects/Author/Model/tex.bmp"), b"").unwrap();
+        let added = stamp(&root);
+        assert_ne!(added, resized);
+
+        fs::remove_file(root.join("Sceneryobjects/Author/Model/tex.bmp")).unwrap();
+        assert_eq!(stamp(&root), resized);
+
+        // Other subtrees do not affect the stamp
+        fs::create_dir_all(root.join("Texture")).unwrap();
+        fs::write(root.join("Texture/road.jpg"), b"").unwrap();
+        assert_eq!(stamp(&root), resized);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,754 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::archive::ArchiveFormat;
+use crate::file_index::{fnv1a, normalize_key, FileIndex, FNV_OFFSET};
+use crate::types::{
+    InstallConflict, InstallPlan, InstallRequest, InstallResult, InstalledBundle, UninstallRequest, UninstallResult,
+};
+use crate::utils::copy_file_with_folders;
+use crate::verify::{extract_bundle, is_manifest};
+
+/// Folder below the OMSI root that holds install logs and backups
+pub const INSTALL_DIR_NAME: &str = "OMSIMapBundler";
+
+/// Number of staging folders created by this process, to keep them apart
+static STAGING_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// What an install did with one bundled file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallAction {
+    /// The file did not exist before
+    Added,
+    /// An existing file with different content was backed up and replaced
+    Overwritten,
+    /// The file already existed with the same content
+    Identical,
+    /// An existing file with different content was left in place
+    Kept,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledFile {
+    /// Path relative to the OMSI root
+    pub path: String,
+    /// Content hash of the bundled version
+    pub hash: String,
+    pub action: InstallAction,
+}
+
+/// Record of one install, stored in `OMSIMapBundler\installs` below the OMSI root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallLog {
+    pub bundle: String,
+    pub archive: String,
+    /// Unix seconds
+    pub installed_at: u64,
+    pub backup_dir: Option<String>,
+    pub files: Vec<InstalledFile>,
+}
+
+/// Decision for a conflicting file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resolution {
+    Keep,
+    Overwrite,
+}
+
+impl Resolution {
+    fn parse(name: &str) -> Result<Resolution, String> {
+        match name.to_lowercase().as_str() {
+            "keep" => Ok(Resolution::Keep),
+            "overwrite" => Ok(Resolution::Overwrite),
+            other => Err(format!("Unknown conflict resolution: {}", other)),
+        }
+    }
+}
+
+/// One extracted file and where it goes
+struct StagedFile {
+    /// Path relative to the OMSI root (backslashes)
+    path: String,
+    staged: PathBuf,
+    target: PathBuf,
+    hash: String,
+}
+
+/// A bundle extracted into a staging folder, compared against the OMSI root
+struct Staged {
+    new_files: Vec<StagedFile>,
+    identical: Vec<StagedFile>,
+    conflicts: Vec<(StagedFile, InstallConflict)>,
+}
+
+/// Folder holding the install logs of an OMSI root
+pub fn installs_dir(omsi_root: &Path) -> PathBuf {
+    omsi_root.join(INSTALL_DIR_NAME).join("installs")
+}
+
+fn backups_dir(omsi_root: &Path) -> PathBuf {
+    omsi_root.join(INSTALL_DIR_NAME).join("backups")
+}
+
+/// Content hash used to compare bundled and installed files
+pub fn file_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut hash = FNV_OFFSET;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hash = fnv1a(hash, &buffer[..read]);
+    }
+    Ok(format!("{:016x}", hash))
+}
+
+/// Bundle name without extension and volume number
+fn bundle_name(format: ArchiveFormat, file_name: &str) -> String {
+    let stem = format.stem(file_name);
+    match stem.rsplit_once(".part") {
+        Some((name, index)) if !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()) => name.to_string(),
+        _ => stem,
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Extract the bundle into `dir` and compare every file with the OMSI root
+fn stage(archive_path: &Path, format: ArchiveFormat, omsi_root: &Path, dir: &Path) -> Result<Staged, String> {
+    if dir.exists() {
+        let _ = fs::remove_dir_all(dir);
+    }
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create temp folder: {}", e))?;
+
+    let bundle = extract_bundle(archive_path, format, dir)?;
+    if !bundle.missing_volumes.is_empty() {
+        return Err(format!("Missing volumes: {}", bundle.missing_volumes.join(", ")));
+    }
+    let corrupt: Vec<&String> = bundle.archives.iter().flat_map(|(_, e)| &e.corrupt).collect();
+    if !corrupt.is_empty() {
+        return Err(format!("{} corrupt entries, e.g. {}", corrupt.len(), corrupt[0]));
+    }
+
+    let index = FileIndex::build(omsi_root);
+    let mut staged = Staged { new_files: Vec::new(), identical: Vec::new(), conflicts: Vec::new() };
+    for name in bundle.archives.iter().flat_map(|(_, e)| &e.files) {
+        // Assets always live in a folder; files at the archive root are bundle metadata
+        // (manifests, the README) and do not belong in the OMSI root
+        if !name.contains('/') {
+            if !is_manifest(name) {
+                log_info!("Not installing {} from the archive root", name);
+            }
+            continue;
+        }
+        let path = name.replace('/', "\\");
+        let staged_path = dir.join(name);
+        let hash = file_hash(&staged_path).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+
+        if !index.is_file(&path) {
+            staged.new_files.push(StagedFile { target: omsi_root.join(name), path, staged: staged_path, hash });
+            continue;
+        }
+        // Existing files keep their on-disk casing
+        let target = index.full_path(&path);
+        let existing_hash = file_hash(&target).map_err(|e| format!("Failed to read {}: {}", target.display(), e))?;
+        let file = StagedFile { path, staged: staged_path, target, hash };
+        if existing_hash == file.hash {
+            staged.identical.push(file);
+        } else {
+            let conflict = InstallConflict {
+                path: file.path.clone(),
+                existing_size: fs::metadata(&file.target).map(|m| m.len()).unwrap_or(0),
+                bundle_size: fs::metadata(&file.staged).map(|m| m.len()).unwrap_or(0),
+                existing_hash,
+                bundle_hash: file.hash.clone(),
+            };
+            staged.conflicts.push((file, conflict));
+        }
+    }
+    Ok(staged)
+}
+
+/// Validate the request and return (archive, format, OMSI root, staging folder)
+fn prepare(request: &InstallRequest) -> Result<(PathBuf, ArchiveFormat, PathBuf, PathBuf), String> {
+    let archive_path = PathBuf::from(&request.archive_path);
+    let file_name = archive_path.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+    let format = ArchiveFormat::from_file_name(file_name)
+        .ok_or_else(|| format!("Unsupported archive: {}", file_name))?;
+    if !archive_path.is_file() {
+        return Err(format!("Archive not found: {}", archive_path.display()));
+    }
+    let omsi_root = PathBuf::from(&request.omsi_root);
+    if !omsi_root.is_dir() {
+        return Err(format!("OMSI folder not found: {}", omsi_root.display()));
+    }
+    // Every call stages into its own folder, so plans and installs of the same bundle can run side by side
+    let dir = std::env::temp_dir().join(format!(
+        "omsi_install_{}_{}_{}",
+        format.stem(file_name),
+        std::process::id(),
+        STAGING_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    Ok((archive_path, format, omsi_root, dir))
+}
+
+/// Compare a bundle with an OMSI root without changing anything
+pub fn plan_install(request: InstallRequest) -> InstallPlan {
+    let failure = |error: String| InstallPlan { new_files: Vec::new(), identical: Vec::new(), conflicts: Vec::new(), error: Some(error) };
+    let (archive_path, format, omsi_root, dir) = match prepare(&request) {
+        Ok(prepared) => prepared,
+        Err(e) => return failure(e),
+    };
+
+    let staged = stage(&archive_path, format, &omsi_root, &dir);
+    let _ = fs::remove_dir_all(&dir);
+    match staged {
+        Ok(staged) => {
+            log_info!(
+                "Install plan: {} new, {} identical, {} conflicts",
+                staged.new_files.len(),
+                staged.identical.len(),
+                staged.conflicts.len()
+            );
+            InstallPlan {
+                new_files: staged.new_files.into_iter().map(|f| f.path).collect(),
+                identical: staged.identical.into_iter().map(|f| f.path).collect(),
+                conflicts: staged.conflicts.into_iter().map(|(_, c)| c).collect(),
+                error: None,
+            }
+        }
+        Err(e) => failure(e),
+    }
+}
+
+/// Extract a bundle into an OMSI root. Conflicting files are kept or overwritten as requested;
+/// overwritten files are backed up first. Nothing is written while a conflict has no decision.
+pub fn install_bundle(request: InstallRequest) -> InstallResult {
+    let failure = |error: String| InstallResult {
+        success: false,
+        installed: Vec::new(),
+        kept: Vec::new(),
+        overwritten: Vec::new(),
+        backup_dir: None,
+        log_path: None,
+        unresolved: Vec::new(),
+        error: Some(error),
+    };
+    let (archive_path, format, omsi_root, dir) = match prepare(&request) {
+        Ok(prepared) => prepared,
+        Err(e) => return failure(e),
+    };
+    let default = match request.default_resolution.as_deref().map(Resolution::parse) {
+        Some(Ok(resolution)) => Some(resolution),
+        Some(Err(e)) => return failure(e),
+        None => None,
+    };
+    let mut resolutions = HashMap::new();
+    for (path, name) in request.resolutions.iter().flatten() {
+        match Resolution::parse(name) {
+            Ok(resolution) => resolutions.insert(normalize_key(path), resolution),
+            Err(e) => return failure(e),
+        };
+    }
+
+    let staged = match stage(&archive_path, format, &omsi_root, &dir) {
+        Ok(staged) => staged,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&dir);
+            return failure(e);
+        }
+    };
+
+    let unresolved: Vec<InstallConflict> = staged.conflicts.iter()
+        .filter(|(file, _)| !resolutions.contains_key(&normalize_key(&file.path)) && default.is_none())
+        .map(|(_, conflict)| conflict.clone())
+        .collect();
+    if !unresolved.is_empty() {
+        let _ = fs::remove_dir_all(&dir);
+        return InstallResult {
+            unresolved,
+            ..failure("Some files conflict with existing files, choose keep or overwrite for each".to_string())
+        };
+    }
+
+    let file_name = archive_path.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+    let bundle = bundle_name(format, file_name);
+    let installed_at = unix_now();
+    let backup_dir = backups_dir(&omsi_root).join(format!("{}-{}", bundle, installed_at));
+    let mut log = InstallLog {
+        bundle: bundle.clone(),
+        archive: archive_path.to_string_lossy().to_string(),
+        installed_at,
+        backup_dir: None,
+        files: Vec::new(),
+    };
+    let mut result = InstallResult { error: None, ..failure(String::new()) };
+
+    let mut error = None;
+    for file in &staged.new_files {
+        if let Err(e) = copy_file_with_folders(&file.staged, &file.target) {
+            error = Some(format!("Failed to install {}: {}", file.path, e));
+            break;
+        }
+        log.files.push(InstalledFile { path: file.path.clone(), hash: file.hash.clone(), action: InstallAction::Added });
+        result.installed.push(file.path.clone());
+    }
+    for file in staged.identical.iter().filter(|_| error.is_none()) {
+        log.files.push(InstalledFile { path: file.path.clone(), hash: file.hash.clone(), action: InstallAction::Identical });
+    }
+    for (file, _) in staged.conflicts.iter().filter(|_| error.is_none()) {
+        let resolution = resolutions.get(&normalize_key(&file.path)).copied().or(default).unwrap_or(Resolution::Keep);
+        if resolution == Resolution::Keep {
+            log.files.push(InstalledFile { path: file.path.clone(), hash: file.hash.clone(), action: InstallAction::Kept });
+            result.kept.push(file.path.clone());
+            continue;
+        }
+
+        let backup = backup_dir.join(file.path.replace('\\', "/"));
+        if let Err(e) = copy_file_with_folders(&file.target, &backup) {
+            error = Some(format!("Failed to back up {}: {}", file.path, e));
+            break;
+        }
+        log.backup_dir = Some(backup_dir.to_string_lossy().to_string());
+        if let Err(e) = copy_file_with_folders(&file.staged, &file.target) {
+            error = Some(format!("Failed to install {}: {}", file.path, e));
+            break;
+        }
+        log.files.push(InstalledFile { path: file.path.clone(), hash: file.hash.clone(), action: InstallAction::Overwritten });
+        result.installed.push(file.path.clone());
+        result.overwritten.push(file.path.clone());
+    }
+    let _ = fs::remove_dir_all(&dir);
+
+    // Written even after a failure, so a partial install can still be uninstalled
+    let log_path = installs_dir(&omsi_root).join(format!("{}-{}.json", bundle, installed_at));
+    let written = serde_json::to_vec_pretty(&log)
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            fs::create_dir_all(installs_dir(&omsi_root)).map_err(|e| e.to_string())?;
+            fs::write(&log_path, json).map_err(|e| e.to_string())
+        });
+    match written {
+        Ok(()) => result.log_path = Some(log_path.to_string_lossy().to_string()),
+        Err(e) => log_warn!("Failed to write install log {}: {}", log_path.display(), e),
+    }
+
+    log_info!(
+        "Installed {} into {}: {} files written, {} overwritten, {} kept",
+        bundle,
+        omsi_root.display(),
+        result.installed.len(),
+        result.overwritten.len(),
+        result.kept.len()
+    );
+    result.backup_dir = log.backup_dir;
+    result.success = error.is_none();
+    result.error = error;
+    result
+}
+
+/// All install logs of an OMSI root, oldest first
+fn load_install_logs(omsi_root: &Path) -> Vec<(PathBuf, InstallLog)> {
+    let mut logs: Vec<(PathBuf, InstallLog)> = fs::read_dir(installs_dir(omsi_root))
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| p.extension().map(|e| e == "json").unwrap_or(false))
+        .filter_map(|p| {
+            let log = fs::read(&p).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok());
+            if log.is_none() {
+                log_warn!("Ignoring unreadable install log {}", p.display());
+            }
+            log.map(|log| (p, log))
+        })
+        .collect();
+    logs.sort_by_key(|(_, log)| log.installed_at);
+    logs
+}
+
+/// Bundles installed into an OMSI root
+pub fn list_installs(omsi_root: String) -> Vec<InstalledBundle> {
+    load_install_logs(Path::new(&omsi_root))
+        .into_iter()
+        .map(|(path, log)| InstalledBundle {
+            files: log.files.iter()
+                .filter(|f| matches!(f.action, InstallAction::Added | InstallAction::Overwritten))
+                .count(),
+            bundle: log.bundle,
+            installed_at: log.installed_at,
+            log_path: path.to_string_lossy().to_string(),
+        })
+        .collect()
+}
+
+/// Remove an installed bundle.
+///
+/// Only files the bundle added or overwrote are touched, and only while their content still
+/// matches the install. Files listed in any other install log stay in place. Overwritten files
+/// are restored from the backup, added files are deleted along with folders left empty.
+pub fn uninstall_bundle(request: UninstallRequest) -> UninstallResult {
+    let failure = |error: String| UninstallResult {
+        success: false,
+        removed: Vec::new(),
+        restored: Vec::new(),
+        shared: Vec::new(),
+        modified: Vec::new(),
+        error: Some(error),
+    };
+    let omsi_root = PathBuf::from(&request.omsi_root);
+    let log_path = match Path::new(&request.install_log) {
+        p if p.is_absolute() => p.to_path_buf(),
+        p => installs_dir(&omsi_root).join(p),
+    };
+
+    let logs = load_install_logs(&omsi_root);
+    let log = match logs.iter().find(|(p, _)| p == &log_path) {
+        Some((_, log)) => log.clone(),
+        None => return failure(format!("Install log not found: {}", log_path.display())),
+    };
+
+    // Reference counts: every file any other installed bundle brought along or relies on
+    let mut others: Vec<(PathBuf, InstallLog)> = logs.into_iter().filter(|(p, _)| p != &log_path).collect();
+    let shared_keys: HashSet<String> = others.iter()
+        .flat_map(|(_, other)| other.files.iter().map(|f| normalize_key(&f.path)))
+        .collect();
+    let mut changed_logs = HashSet::new();
+
+    let index = FileIndex::build(&omsi_root);
+    let mut result = UninstallResult { error: None, ..failure(String::new()) };
+    let mut emptied = Vec::new();
+    for file in &log.files {
+        if !matches!(file.action, InstallAction::Added | InstallAction::Overwritten) {
+            continue;
+        }
+        if shared_keys.contains(&normalize_key(&file.path)) {
+            if let Some(i) = transfer_ownership(&omsi_root, &mut others, &log, file) {
+                changed_logs.insert(i);
+            }
+            result.shared.push(file.path.clone());
+            continue;
+        }
+        if !index.is_file(&file.path) {
+            continue;
+        }
+        let target = index.full_path(&file.path);
+        if file_hash(&target).map(|h| h != file.hash).unwrap_or(true) {
+            result.modified.push(file.path.clone());
+            continue;
+        }
+
+        let backup = log.backup_dir.as_ref()
+            .map(|dir| Path::new(dir).join(file.path.replace('\\', "/")))
+            .filter(|_| file.action == InstallAction::Overwritten);
+        let outcome = match &backup {
+            Some(backup) => fs::copy(backup, &target).map(|_| result.restored.push(file.path.clone())),
+            None => fs::remove_file(&target).map(|_| {
+                result.removed.push(file.path.clone());
+                emptied.extend(target.parent().map(Path::to_path_buf));
+            }),
+        };
+        if let Err(e) = outcome {
+            result.error = Some(format!("Failed to uninstall {}: {}", file.path, e));
+            break;
+        }
+    }
+
+    // Remove folders the bundle created, innermost first; non-empty folders stay
+    emptied.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    for dir in emptied {
+        for ancestor in dir.ancestors() {
+            if ancestor == omsi_root || !ancestor.starts_with(&omsi_root) || fs::remove_dir(ancestor).is_err() {
+                break;
+            }
+        }
+    }
+
+    for i in changed_logs {
+        let (path, other) = &others[i];
+        let written = serde_json::to_vec_pretty(other).map_err(io::Error::other).and_then(|json| fs::write(path, json));
+        if let Err(e) = written {
+            log_warn!("Failed to update install log {}: {}", path.display(), e);
+        }
+    }
+
+    if result.error.is_none() {
+        if let Some(dir) = &log.backup_dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+        if let Err(e) = fs::remove_file(&log_path) {
+            log_warn!("Failed to remove install log {}: {}", log_path.display(), e);
+        }
+    }
+
+    log_info!(
+        "Uninstalled {}: {} removed, {} restored, {} shared, {} modified",
+        log.bundle,
+        result.removed.len(),
+        result.restored.len(),
+        result.shared.len(),
+        result.modified.len()
+    );
+    result.success = result.error.is_none();
+    result
+}
+
+/// Hand a shared file over to the oldest other bundle that only relied on it, so that bundle
+/// removes (or restores) it when it is uninstalled itself. Returns the index of the changed log.
+fn transfer_ownership(
+    omsi_root: &Path,
+    others: &mut [(PathBuf, InstallLog)],
+    log: &InstallLog,
+    file: &InstalledFile,
+) -> Option<usize> {
+    let key = normalize_key(&file.path);
+    let (i, entry_index) = others.iter().enumerate().find_map(|(i, (_, other))| {
+        other.files.iter()
+            .position(|f| normalize_key(&f.path) == key)
+            .filter(|&pos| matches!(other.files[pos].action, InstallAction::Identical | InstallAction::Kept))
+            .map(|pos| (i, pos))
+    })?;
+    let other = &mut others[i].1;
+
+    if file.action == InstallAction::Overwritten {
+        // The original moves to the new owner's backup folder
+        let rel = file.path.replace('\\', "/");
+        let source = Path::new(log.backup_dir.as_ref()?).join(&rel);
+        let backup_dir = other.backup_dir.clone().unwrap_or_else(|| {
+            backups_dir(omsi_root).join(format!("{}-{}", other.bundle, other.installed_at)).to_string_lossy().to_string()
+        });
+        if let Err(e) = copy_file_with_folders(&source, &Path::new(&backup_dir).join(&rel)) {
+            log_warn!("Failed to move backup of {}: {}", file.path, e);
+            return None;
+        }
+        other.backup_dir = Some(backup_dir);
+    }
+    other.files[entry_index].action = file.action;
+    other.files[entry_index].hash = file.hash.clone();
+    Some(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use crate::test_utils::test_dir;
+
+    // Test folder with an OMSI root in `OMSI`
+    fn install_dir(name: &str) -> PathBuf {
+        let dir = test_dir(&format!("install_{}", name));
+        fs::create_dir_all(dir.join("OMSI/Texture")).unwrap();
+        dir
+    }
+
+    fn write_bundle(path: &Path, files: &[(&str, &str)]) -> String {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in files {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn request(archive: &str, omsi_root: &Path, resolutions: &[(&str, &str)], default: Option<&str>) -> InstallRequest {
+        InstallRequest {
+            archive_path: archive.to_string(),
+            omsi_root: omsi_root.to_string_lossy().to_string(),
+            resolutions: Some(resolutions.iter().map(|(p, r)| (p.to_string(), r.to_string())).collect()),
+            default_resolution: default.map(str::to_string),
+        }
+    }
+
+    fn sorted(mut paths: Vec<String>) -> Vec<String> {
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn plans_new_identical_and_conflicting_files() {
+        let dir = install_dir("plan");
+        let root = dir.join("OMSI");
+        fs::write(root.join("Texture/same.dds"), "same").unwrap();
+        fs::write(root.join("Texture/road.dds"), "old road").unwrap();
+        let archive = write_bundle(&dir.join("Roads.zip"), &[
+            ("Texture/same.dds", "same"),
+            ("Texture/road.dds", "new road"),
+            ("Texture/grass.dds", "grass"),
+        ]);
+
+        let plan = plan_install(request(&archive, &root, &[], None));
+        assert_eq!(plan.error, None);
+        assert_eq!(plan.new_files, vec!["Texture\\grass.dds".to_string()]);
+        assert_eq!(plan.identical, vec!["Texture\\same.dds".to_string()]);
+        assert_eq!(plan.conflicts.len(), 1);
+        assert_eq!(plan.conflicts[0].path, "Texture\\road.dds");
+        assert_eq!(plan.conflicts[0].existing_size, 8);
+        // Planning changes nothing
+        assert!(!root.join("Texture/grass.dds").exists());
+        assert!(!installs_dir(&root).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn leaves_out_files_at_the_archive_root() {
+        let dir = install_dir("root_files");
+        let root = dir.join("OMSI");
+        let archive = write_bundle(&dir.join("Roads.zip"), &[
+            ("README.txt", "read me"),
+            ("Roads.maps.json", "{}"),
+            ("Texture/grass.dds", "grass"),
+        ]);
+
+        assert_eq!(plan_install(request(&archive, &root, &[], None)).new_files, vec!["Texture\\grass.dds".to_string()]);
+        let result = install_bundle(request(&archive, &root, &[], None));
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.installed, vec!["Texture\\grass.dds".to_string()]);
+        assert!(!root.join("README.txt").exists());
+        assert!(!root.join("Roads.maps.json").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn refuses_unresolved_conflicts() {
+        let dir = install_dir("unresolved");
+        let root = dir.join("OMSI");
+        fs::write(root.join("Texture/road.dds"), "old road").unwrap();
+        fs::write(root.join("Texture/kerb.dds"), "old kerb").unwrap();
+        let archive = write_bundle(&dir.join("Roads.zip"), &[
+            ("Texture/road.dds", "new road"),
+            ("Texture/kerb.dds", "new kerb"),
+            ("Texture/grass.dds", "grass"),
+        ]);
+
+        let result = install_bundle(request(&archive, &root, &[("Texture\\road.dds", "overwrite")], None));
+        assert!(!result.success);
+        assert_eq!(result.unresolved.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(), vec!["Texture\\kerb.dds"]);
+        assert!(!root.join("Texture/grass.dds").exists());
+        assert_eq!(fs::read_to_string(root.join("Texture/road.dds")).unwrap(), "old road");
+        assert!(!installs_dir(&root).exists());
+
+        assert!(install_bundle(request(&archive, &root, &[("Texture\\road.dds", "replace")], Some("keep"))).error.is_some());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keeps_or_overwrites_conflicts_with_backup() {
+        let dir = install_dir("resolve");
+        let root = dir.join("OMSI");
+        fs::write(root.join("Texture/road.dds"), "old road").unwrap();
+        fs::write(root.join("Texture/kerb.dds"), "old kerb").unwrap();
+        fs::write(root.join("Texture/same.dds"), "same").unwrap();
+        let archive = write_bundle(&dir.join("Roads.zip"), &[
+            ("Texture/road.dds", "new road"),
+            ("Texture/kerb.dds", "new kerb"),
+            ("Texture/same.dds", "same"),
+            ("Texture/grass.dds", "grass"),
+        ]);
+
+        let result = install_bundle(request(&archive, &root, &[("texture\\ROAD.dds", "overwrite")], Some("keep")));
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(sorted(result.installed), vec!["Texture\\grass.dds".to_string(), "Texture\\road.dds".to_string()]);
+        assert_eq!(result.overwritten, vec!["Texture\\road.dds".to_string()]);
+        assert_eq!(result.kept, vec!["Texture\\kerb.dds".to_string()]);
+
+        assert_eq!(fs::read_to_string(root.join("Texture/road.dds")).unwrap(), "new road");
+        assert_eq!(fs::read_to_string(root.join("Texture/kerb.dds")).unwrap(), "old kerb");
+        assert_eq!(fs::read_to_string(root.join("Texture/grass.dds")).unwrap(), "grass");
+        let backup_dir = PathBuf::from(result.backup_dir.unwrap());
+        assert_eq!(fs::read_to_string(backup_dir.join("Texture/road.dds")).unwrap(), "old road");
+
+        let log: InstallLog = serde_json::from_slice(&fs::read(result.log_path.unwrap()).unwrap()).unwrap();
+        let action = |path: &str| log.files.iter().find(|f| f.path == path).unwrap().action;
+        assert_eq!(action("Texture\\road.dds"), InstallAction::Overwritten);
+        assert_eq!(action("Texture\\kerb.dds"), InstallAction::Kept);
+        assert_eq!(action("Texture\\same.dds"), InstallAction::Identical);
+        assert_eq!(action("Texture\\grass.dds"), InstallAction::Added);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stages_every_call_in_its_own_folder() {
+        let dir = install_dir("staging");
+        let archive = write_bundle(&dir.join("Roads.zip"), &[("Texture/grass.dds", "grass")]);
+        let request = request(&archive, &dir.join("OMSI"), &[], None);
+        let (_, _, _, first) = prepare(&request).unwrap();
+        let (_, _, _, second) = prepare(&request).unwrap();
+        assert_ne!(first, second);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn uninstall(omsi_root: &Path, log_path: &str) -> UninstallResult {
+        uninstall_bundle(UninstallRequest {
+            omsi_root: omsi_root.to_string_lossy().to_string(),
+            install_log: log_path.to_string(),
+        })
+    }
+
+    #[test]
+    fn shared_files_stay_until_their_last_bundle_is_removed() {
+        let dir = install_dir("shared");
+        let root = dir.join("OMSI");
+        let first = write_bundle(&dir.join("Roads.zip"), &[("Texture/shared.dds", "shared"), ("Texture/road.dds", "road")]);
+        let second = write_bundle(&dir.join("Trees.zip"), &[("Texture/shared.dds", "shared"), ("Texture/tree.dds", "tree")]);
+        let first = install_bundle(request(&first, &root, &[], None));
+        let second = install_bundle(request(&second, &root, &[], None));
+        assert!(first.success && second.success);
+        let second_log = second.log_path.unwrap();
+
+        let result = uninstall(&root, &first.log_path.unwrap());
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.removed, vec!["Texture\\road.dds".to_string()]);
+        assert_eq!(result.shared, vec!["Texture\\shared.dds".to_string()]);
+        assert!(root.join("Texture/shared.dds").exists());
+        assert!(!root.join("Texture/road.dds").exists());
+
+        // The remaining bundle now owns the shared file
+        let log: InstallLog = serde_json::from_slice(&fs::read(&second_log).unwrap()).unwrap();
+        let shared = log.files.iter().find(|f| f.path == "Texture\\shared.dds").unwrap();
+        assert_eq!(shared.action, InstallAction::Added);
+
+        let result = uninstall(&root, &second_log);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(sorted(result.removed), vec!["Texture\\shared.dds".to_string(), "Texture\\tree.dds".to_string()]);
+        assert!(!root.join("Texture").exists());
+        assert!(list_installs(root.to_string_lossy().to_string()).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn leaves_modified_files_and_restores_overwritten_ones() {
+        let dir = install_dir("modified");
+        let root = dir.join("OMSI");
+        fs::write(root.join("Texture/road.dds"), "old road").unwrap();
+        let archive = write_bundle(&dir.join("Roads.zip"), &[("Texture/road.dds", "new road"), ("Texture/grass.dds", "grass")]);
+        let installed = install_bundle(request(&archive, &root, &[], Some("overwrite")));
+        assert!(installed.success, "{:?}", installed.error);
+        fs::write(root.join("Texture/grass.dds"), "repainted grass").unwrap();
+
+        let result = uninstall(&root, &installed.log_path.unwrap());
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.modified, vec!["Texture\\grass.dds".to_string()]);
+        assert_eq!(result.restored, vec!["Texture\\road.dds".to_string()]);
+        assert!(result.removed.is_empty());
+        assert_eq!(fs::read_to_string(root.join("Texture/grass.dds")).unwrap(), "repainted grass");
+        assert_eq!(fs::read_to_string(root.join("Texture/road.dds")).unwrap(), "old road");
+        assert!(!Path::new(&installed.backup_dir.unwrap()).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
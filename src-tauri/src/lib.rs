@@ -1,15 +1,35 @@
 // Module declarations
+#[macro_use]
+mod diagnostics;
 mod types;
 mod validation;
 mod extraction;
 mod bundling;
+mod bundle_ignore;
 mod utils;
+mod archive;
+mod archive_names;
+mod volumes;
+mod manifest;
+mod plan;
+mod catalogue;
+mod omsi_root;
+mod steam;
+mod verify;
+mod install;
+mod encoding;
+mod file_index;
+mod parse_cache;
+mod provenance;
+mod dependency;
 mod phase1_collection;
 mod phase2_extraction;
 mod phase2_processing;
+#[cfg(test)]
+mod test_utils;
 
 // Re-export types for Tauri commands
-use types::{ValidationResult, DependencyResult, BundleRequest, BundleResult};
+use types::{ValidationResult, DependencyResult, BundleRequest, BundleResult, VerifyRequest, VerifyResult, InstallRequest, InstallPlan, InstallResult, InstalledBundle, UninstallRequest, UninstallResult, OmsiInstallation, MapInfo, BundlePlan, PlanOverrides, PlanOverridesResult};
 use tauri::{async_runtime, Emitter};
 use serde::Serialize;
 use std::sync::Arc;
@@ -65,20 +85,209 @@ async fn create_bundle(app_handle: tauri::AppHandle, request: BundleRequest) ->
     .unwrap_or_else(|_| BundleResult {
         success: false,
         output_path: None,
+        output_paths: vec![],
         error: Some("Bundle task failed".into()),
+        scan_report: None,
+        non_ascii_names: vec![],
+        heuristic_dependencies: vec![],
+        dependencies: vec![],
     })
 }
 
+#[tauri::command]
+async fn get_bundle_plan(request: BundleRequest) -> BundlePlan {
+    async_runtime::spawn_blocking(move || plan::get_bundle_plan(request))
+        .await
+        .unwrap_or_else(|_| BundlePlan {
+            omsi_root: String::new(),
+            overrides: PlanOverrides::default(),
+            nodes: vec![],
+            files: 0,
+            excluded: 0,
+            missing: 0,
+            error: Some("Plan task failed".into()),
+        })
+}
+
+#[tauri::command]
+fn save_plan_overrides(map_folder: String, overrides: PlanOverrides) -> PlanOverridesResult {
+    plan::save_overrides(map_folder, overrides)
+}
+
+#[tauri::command]
+async fn verify_bundle(request: VerifyRequest) -> VerifyResult {
+    async_runtime::spawn_blocking(move || verify::verify_bundle(request))
+        .await
+        .unwrap_or_else(|_| VerifyResult {
+            success: false,
+            files: 0,
+            corrupt_entries: vec![],
+            missing_entries: vec![],
+            unlisted_entries: vec![],
+            missing_volumes: vec![],
+            maps: vec![],
+            unresolved: vec![],
+            error: Some("Verification task failed".into()),
+        })
+}
+
+#[tauri::command]
+async fn plan_install(request: InstallRequest) -> InstallPlan {
+    async_runtime::spawn_blocking(move || install::plan_install(request))
+        .await
+        .unwrap_or_else(|_| InstallPlan {
+            new_files: vec![],
+            identical: vec![],
+            conflicts: vec![],
+            error: Some("Install planning task failed".into()),
+        })
+}
+
+#[tauri::command]
+async fn install_bundle(request: InstallRequest) -> InstallResult {
+    async_runtime::spawn_blocking(move || install::install_bundle(request))
+        .await
+        .unwrap_or_else(|_| InstallResult {
+            success: false,
+            installed: vec![],
+            kept: vec![],
+            overwritten: vec![],
+            backup_dir: None,
+            log_path: None,
+            unresolved: vec![],
+            error: Some("Install task failed".into()),
+        })
+}
+
+#[tauri::command]
+async fn list_installs(omsi_root: String) -> Vec<InstalledBundle> {
+    async_runtime::spawn_blocking(move || install::list_installs(omsi_root))
+        .await
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+async fn discover_omsi_installations() -> Vec<OmsiInstallation> {
+    async_runtime::spawn_blocking(steam::discover_omsi_installations)
+        .await
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+async fn list_maps(omsi_root: String) -> Vec<MapInfo> {
+    async_runtime::spawn_blocking(move || catalogue::list_maps(omsi_root))
+        .await
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+async fn uninstall_bundle(request: UninstallRequest) -> UninstallResult {
+    async_runtime::spawn_blocking(move || install::uninstall_bundle(request))
+        .await
+        .unwrap_or_else(|_| UninstallResult {
+            success: false,
+            removed: vec![],
+            restored: vec![],
+            shared: vec![],
+            modified: vec![],
+            error: Some("Uninstall task failed".into()),
+        })
+}
+
+#[tauri::command]
+fn get_log_file_path() -> String {
+    diagnostics::log_file_path().to_string_lossy().to_string()
+}
+
+/// Command line subcommands. Returns the exit code when `args` name one, otherwise the GUI starts.
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    match args.first().map(String::as_str) {
+        Some("list-maps") => {
+            attach_console();
+            let root = match args.get(1) {
+                Some(root) if std::path::Path::new(root).is_dir() => root,
+                Some(root) => {
+                    eprintln!("OMSI folder not found: {}", root);
+                    return Some(1);
+                }
+                None => {
+                    eprintln!("Usage: omsimapbundler list-maps <OMSI folder>");
+                    return Some(2);
+                }
+            };
+            for map in catalogue::list_maps(root.clone()) {
+                println!("{}\t{}\t{} tiles\t{} entry points", map.folder, map.name, map.tiles, map.entry_points.len());
+            }
+            Some(0)
+        }
+        _ => None,
+    }
+}
+
+/// Release builds on Windows run without a console (`windows_subsystem = "windows"`),
+/// so command line output goes to the console of the shell that started the program
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // Fails harmlessly when there is no parent console or one is already attached
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    diagnostics::init(diagnostics::LogLevel::Info);
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .invoke_handler(tauri::generate_handler![
             validate_map_folder,
             extract_dependencies,
-            create_bundle
+            create_bundle,
+            get_bundle_plan,
+            save_plan_overrides,
+            verify_bundle,
+            plan_install,
+            install_bundle,
+            list_installs,
+            uninstall_bundle,
+            discover_omsi_installations,
+            list_maps,
+            get_log_file_path
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cli(args: &[&str]) -> Option<i32> {
+        run_cli(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn run_cli_handles_arguments() {
+        // No or unknown subcommands start the GUI
+        assert_eq!(cli(&[]), None);
+        assert_eq!(cli(&["--flag"]), None);
+
+        assert_eq!(cli(&["list-maps"]), Some(2));
+        let root = test_utils::test_dir("cli_list_maps");
+        let missing = root.join("missing");
+        assert_eq!(cli(&["list-maps", &missing.to_string_lossy()]), Some(1));
+        std::fs::create_dir_all(root.join("maps")).unwrap();
+        assert_eq!(cli(&["list-maps", &root.to_string_lossy()]), Some(0));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
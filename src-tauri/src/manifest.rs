@@ -0,0 +1,109 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::file_index::{normalize_key, FileIndex};
+use crate::types::Dependency;
+
+/// Assets one map of a multi-map bundle needs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MapAssets {
+    pub map: String,
+    /// Files and folders (with a trailing `\`) outside the map folder, relative to the OMSI root
+    pub assets: Vec<String>,
+}
+
+/// Manifest stored at the root of a bundle holding several maps
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MapsManifest {
+    pub bundle: String,
+    pub maps: Vec<MapAssets>,
+    /// Assets needed by more than one map (stored once)
+    pub shared: Vec<String>,
+}
+
+/// Name of the maps manifest stored at the root of a multi-map bundle
+pub fn maps_manifest_name(bundle: &str) -> String {
+    format!("{}.maps.json", bundle)
+}
+
+/// Describe which map needs which asset. `maps` holds the name and folder (relative to the OMSI root)
+/// of every map, `map_deps` their resolved dependencies in the same order; only assets found in the OMSI folder are listed.
+pub fn build_maps_manifest(bundle: &str, maps: &[(&str, &str)], map_deps: &[HashSet<Dependency>], index: &FileIndex) -> MapsManifest {
+    let mut users: HashMap<String, usize> = HashMap::new();
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut map_assets = Vec::new();
+
+    for ((map, folder), deps) in maps.iter().zip(map_deps) {
+        let own_prefix = normalize_key(&format!("{}\\", folder));
+        // Keyed by normalized path so differently cased references count once
+        let mut assets: BTreeMap<String, String> = BTreeMap::new();
+        for dep in deps {
+            let asset = match dep {
+                Dependency::Folder(folder) if index.is_dir(folder) => {
+                    format!("{}\\", index.real_path(folder).unwrap_or_else(|| folder.to_string()))
+                }
+                Dependency::File(file) if index.is_file(file) => index.real_path(file).unwrap_or_else(|| file.clone()),
+                _ => continue,
+            };
+            let key = normalize_key(&asset);
+            if !key.starts_with(&own_prefix) {
+                assets.insert(key, asset);
+            }
+        }
+        for (key, asset) in &assets {
+            *users.entry(key.clone()).or_default() += 1;
+            names.entry(key.clone()).or_insert_with(|| asset.clone());
+        }
+        map_assets.push(MapAssets { map: map.to_string(), assets: assets.into_values().collect() });
+    }
+
+    let mut shared: Vec<String> = users.into_iter()
+        .filter(|(_, count)| *count > 1)
+        .filter_map(|(key, _)| names.remove(&key))
+        .collect();
+    shared.sort();
+
+    MapsManifest { bundle: bundle.to_string(), maps: map_assets, shared }
+}
+
+/// Bundled files taken from one asset root
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RootFiles {
+    pub root: String,
+    pub files: Vec<String>,
+}
+
+/// Manifest stored at the root of a bundle built with secondary asset roots,
+/// recording which root every bundled asset came from
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SourcesManifest {
+    pub bundle: String,
+    /// The OMSI root first, then the secondary roots in search order
+    pub roots: Vec<RootFiles>,
+}
+
+/// Name of the sources manifest stored at the root of a bundle
+pub fn sources_manifest_name(bundle: &str) -> String {
+    format!("{}.sources.json", bundle)
+}
+
+/// Group the bundled asset files (relative to the OMSI root) by the root they are read from
+pub fn build_sources_manifest(bundle: &str, roots: &[PathBuf], files: &[String], index: &FileIndex) -> SourcesManifest {
+    let mut grouped: Vec<Vec<String>> = vec![Vec::new(); roots.len()];
+    for file in files.iter().filter(|f| index.is_file(f)) {
+        let root = index.root_of(file);
+        if let Some(pos) = roots.iter().position(|r| r == root) {
+            grouped[pos].push(index.real_path(file).unwrap_or_else(|| file.clone()));
+        }
+    }
+
+    SourcesManifest {
+        bundle: bundle.to_string(),
+        roots: roots.iter().zip(grouped)
+            .map(|(root, mut files)| {
+                files.sort();
+                RootFiles { root: root.to_string_lossy().to_string(), files }
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,99 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Executable found in the root of every OMSI 2 installation
+const OMSI_EXE: &str = "omsi.exe";
+
+/// Folders every OMSI 2 installation has, used when the executable is missing (e.g. a copied asset tree)
+const STANDARD_FOLDERS: &[&str] = &["sceneryobjects", "splines", "vehicles"];
+
+/// Whether `dir` looks like an OMSI 2 root: it contains `Omsi.exe` or all standard asset folders
+pub fn is_omsi_root(dir: &Path) -> bool {
+    let names: Vec<(String, bool)> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| (e.file_name().to_string_lossy().to_lowercase(), e.path().is_dir()))
+            .collect(),
+        Err(_) => return false,
+    };
+    let has = |name: &str, is_dir: bool| names.iter().any(|(n, d)| n == name && *d == is_dir);
+
+    has(OMSI_EXE, false) || STANDARD_FOLDERS.iter().all(|folder| has(folder, true))
+}
+
+/// Nearest folder above `path` (a map folder) that is an OMSI 2 root
+pub fn detect_omsi_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|dir| is_omsi_root(dir))
+        .map(Path::to_path_buf)
+}
+
+/// Path of a map folder inside the bundle, relative to the OMSI root (`maps\Author\Map`).
+/// Maps kept outside the root are placed in `maps\<name>`.
+pub fn map_rel_path(omsi_root: &Path, map_path: &Path, name: &str) -> String {
+    match map_path.strip_prefix(omsi_root) {
+        Ok(rel) if !rel.as_os_str().is_empty() => rel.to_string_lossy().replace('/', "\\"),
+        _ => format!("maps\\{}", name),
+    }
+}
+
+/// Map folders under `root\maps`, sorted. Maps may be nested (`maps\Author\Map`),
+/// every folder with a global.cfg counts.
+pub fn list_map_folders(root: &Path) -> Vec<PathBuf> {
+    let mut maps: Vec<PathBuf> = WalkDir::new(root.join("maps"))
+        .min_depth(2)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name().eq_ignore_ascii_case("global.cfg"))
+        .filter_map(|e| e.path().parent().map(Path::to_path_buf))
+        .collect();
+    maps.sort();
+    maps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_dir;
+
+    fn test_root(name: &str) -> PathBuf {
+        let dir = test_dir(&format!("root_{}", name));
+        fs::create_dir_all(dir.join("OMSI 2/maps/Author/Grundorf")).unwrap();
+        fs::create_dir_all(dir.join("OMSI 2/maps/Solo")).unwrap();
+        fs::write(dir.join("OMSI 2/maps/Author/Grundorf/global.cfg"), "").unwrap();
+        fs::write(dir.join("OMSI 2/maps/Solo/global.cfg"), "").unwrap();
+        dir
+    }
+
+    #[test]
+    fn detects_the_root_above_nested_maps() {
+        let dir = test_root("nested");
+        let root = dir.join("OMSI 2");
+        let map = root.join("maps/Author/Grundorf");
+        assert_eq!(detect_omsi_root(&map), None);
+
+        fs::write(root.join("Omsi.exe"), "").unwrap();
+        assert_eq!(detect_omsi_root(&map), Some(root.clone()));
+        assert_eq!(map_rel_path(&root, &map, "Grundorf"), "maps\\Author\\Grundorf");
+        assert_eq!(list_map_folders(&root), vec![map.clone(), root.join("maps/Solo")]);
+
+        // Without the executable, the standard asset folders identify the root
+        fs::remove_file(root.join("Omsi.exe")).unwrap();
+        for folder in ["Sceneryobjects", "Splines", "Vehicles"] {
+            fs::create_dir_all(root.join(folder)).unwrap();
+        }
+        assert_eq!(detect_omsi_root(&map), Some(root.clone()));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn places_maps_outside_the_root_by_name() {
+        let dir = test_root("outside");
+        let root = dir.join("OMSI 2");
+        assert_eq!(map_rel_path(&root, &dir.join("Work/Grundorf"), "Grundorf"), "maps\\Grundorf");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+use serde::{Deserialize, Serialize};
+use crate::types::{Dependency, DependencySource};
+use crate::utils::app_cache_dir;
+
+/// Cache file name inside the application cache directory
+const CACHE_FILE_NAME: &str = "parse_cache.json";
+
+/// Layout of the cached results; entries of another format are discarded.
+/// 2: heuristic results carry their source.
+/// 3: folder dependencies are stored apart from files.
+/// 4: stamps are chained and cover the folders a result was looked up in.
+const CACHE_FORMAT: u32 = 4;
+
+/// Cached extraction result for a single file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CacheEntry {
+    size: u64,
+    modified: u64,
+    /// Fingerprint of everything besides the file itself that the result depends on
+    /// (e.g. the folders searched for textures). Zero for pure parses.
+    stamp: u64,
+    deps: Vec<String>,
+    /// Folders the file depends on as a whole
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    folders: Vec<String>,
+    /// Sources of the deps found by heuristics; all others were found exactly
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    sources: HashMap<String, DependencySource>,
+    /// Further folders the result was looked up in; their contents are part of `stamp`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    lookups: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: String,
+    #[serde(default)]
+    format: u32,
+    entries: HashMap<String, CacheEntry>,
+}
+
+/// On-disk cache of per-file extraction results, shared between bundle runs.
+///
+/// Entries are keyed by absolute path and validated against the file's size and
+/// modification time. The whole cache is discarded when the bundler version changes.
+pub struct ParseCache {
+    path: Option<PathBuf>,
+    entries: RwLock<HashMap<String, CacheEntry>>,
+    dirty: AtomicBool,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl ParseCache {
+    /// Load the cache from the default location in the application cache directory
+    pub fn load_default() -> ParseCache {
+        ParseCache::load(&app_cache_dir().join(CACHE_FILE_NAME))
+    }
+
+    /// Load the cache from a file, starting empty if it is missing, corrupt or from another version
+    pub fn load(path: &Path) -> ParseCache {
+        let entries = fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<CacheFile>(&bytes).ok())
+            .filter(|file| file.version == env!("CARGO_PKG_VERSION") && file.format == CACHE_FORMAT)
+            .map(|file| file.entries)
+            .unwrap_or_default();
+
+        log_debug!("[CACHE] Loaded {} cached entries from {:?}", entries.len(), path);
+
+        ParseCache {
+            path: Some(path.to_path_buf()),
+            entries: RwLock::new(entries),
+            dirty: AtomicBool::new(false),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Look up cached dependencies together with the sources of the ones found by heuristics.
+    /// Returns None if the file changed since it was cached. `stamp` computes the current stamp
+    /// from the folders the result was looked up in (see `insert_tracked`).
+    pub fn get_tracked(&self, full_path: &Path, stamp: impl FnOnce(&[String]) -> u64) -> Option<(Vec<Dependency>, HashMap<String, DependencySource>)> {
+        let entry = self.get_entry(full_path, stamp)?;
+        let dependencies = entry.deps.into_iter()
+            .map(Dependency::File)
+            .chain(entry.folders.into_iter().map(Dependency::Folder))
+            .collect();
+        Some((dependencies, entry.sources))
+    }
+
+    fn get_entry(&self, full_path: &Path, stamp: impl FnOnce(&[String]) -> u64) -> Option<CacheEntry> {
+        let (size, modified) = file_signature(full_path)?;
+        let entry = self.entries.read().unwrap().get(&cache_key(full_path))
+            .filter(|entry| entry.size == size && entry.modified == modified)
+            .cloned();
+        match entry {
+            Some(entry) if entry.stamp == stamp(&entry.lookups) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(entry)
+            }
+            _ => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Store freshly extracted dependencies with the sources of the ones found by heuristics.
+    /// `lookups` are further folders the result depends on; `stamp` must cover them
+    /// the way the `get_tracked` caller computes it (empty for pure parses).
+    pub fn insert_tracked(&self, full_path: &Path, stamp: u64, dependencies: Vec<Dependency>, sources: HashMap<String, DependencySource>, lookups: Vec<String>) {
+        let (folders, files): (Vec<Dependency>, Vec<Dependency>) = dependencies.into_iter().partition(Dependency::is_folder);
+        let paths = |deps: Vec<Dependency>| deps.iter().map(|d| d.path().to_string()).collect();
+        let entry = CacheEntry { stamp, deps: paths(files), folders: paths(folders), sources, lookups, ..CacheEntry::default() };
+        self.insert_entry(full_path, entry);
+    }
+
+    fn insert_entry(&self, full_path: &Path, mut entry: CacheEntry) {
+        if let Some((size, modified)) = file_signature(full_path) {
+            entry.size = size;
+            entry.modified = modified;
+            self.entries.write().unwrap().insert(cache_key(full_path), entry);
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Write the cache back to disk if anything changed
+    pub fn save(&self) -> std::io::Result<()> {
+        log_debug!(
+            "[CACHE] {} hits, {} misses",
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed)
+        );
+
+        let path = match &self.path {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+        if !self.dirty.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let file = CacheFile {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            format: CACHE_FORMAT,
+            entries: self.entries.read().unwrap().clone(),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_vec(&file).map_err(std::io::Error::other)?;
+        // Write to a temporary file first so an interrupted save never corrupts the cache
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, path)?;
+        self.dirty.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Default for ParseCache {
+    /// An in-memory cache that is never persisted
+    fn default() -> Self {
+        ParseCache {
+            path: None,
+            entries: RwLock::new(HashMap::new()),
+            dirty: AtomicBool::new(false),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+}
+
+fn cache_key(full_path: &Path) -> String {
+    full_path.to_string_lossy().replace('/', "\\").to_lowercase()
+}
+
+/// Size and modification time (seconds since UNIX epoch) of a file
+fn file_signature(full_path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(full_path).ok()?;
+    let modified = metadata.modified().ok()?
+        .duration_since(UNIX_EPOCH).ok()?
+        .as_secs();
+    Some((metadata.len(), modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_dir;
+    use std::time::{Duration, SystemTime};
+
+    fn dependencies() -> Vec<Dependency> {
+        vec![Dependency::File("Texture\\a.jpg".to_string()), Dependency::Folder("Sceneryobjects\\A\\texture".to_string())]
+    }
+
+    #[test]
+    fn round_trips_through_disk() {
+        let dir = test_dir("cache_round_trip");
+        let file = dir.join("House.sco");
+        fs::write(&file, b"[mesh]").unwrap();
+        let sources = HashMap::from([("Texture\\a.jpg".to_string(), DependencySource::PrefixMatch)]);
+
+        let cache = ParseCache::load(&dir.join(CACHE_FILE_NAME));
+        cache.insert_tracked(&file, 7, dependencies(), sources.clone(), vec!["sceneryobjects\\shared".to_string()]);
+        cache.save().unwrap();
+
+        let cache = ParseCache::load(&dir.join(CACHE_FILE_NAME));
+        let (mut cached, cached_sources) = cache.get_tracked(&file, |lookups| {
+            assert_eq!(lookups, ["sceneryobjects\\shared".to_string()]);
+            7
+        }).unwrap();
+        cached.sort();
+        let mut expected = dependencies();
+        expected.sort();
+        assert_eq!(cached, expected);
+        assert_eq!(cached_sources, sources);
+        assert!(cache.get_tracked(&file, |_| 8).is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn discards_other_formats() {
+        let dir = test_dir("cache_format");
+        let file = dir.join("House.sco");
+        fs::write(&file, b"[mesh]").unwrap();
+        let cache_path = dir.join(CACHE_FILE_NAME);
+
+        let cache = ParseCache::load(&cache_path);
+        cache.insert_tracked(&file, 0, dependencies(), HashMap::new(), Vec::new());
+        cache.save().unwrap();
+        assert!(ParseCache::load(&cache_path).get_tracked(&file, |_| 0).is_some());
+
+        for (key, value) in [("format", serde_json::json!(CACHE_FORMAT - 1)), ("version", serde_json::json!("0.0.1"))] {
+            let mut json: serde_json::Value = serde_json::from_slice(&fs::read(&cache_path).unwrap()).unwrap();
+            json[key] = value;
+            let stale = dir.join(format!("{}.json", key));
+            fs::write(&stale, serde_json::to_vec(&json).unwrap()).unwrap();
+            assert!(ParseCache::load(&stale).get_tracked(&file, |_| 0).is_none(), "{}", key);
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn invalidates_changed_files() {
+        let dir = test_dir("cache_changed");
+        let file = dir.join("House.sco");
+        fs::write(&file, b"[mesh]").unwrap();
+
+        let cache = ParseCache::default();
+        cache.insert_tracked(&file, 0, dependencies(), HashMap::new(), Vec::new());
+        assert!(cache.get_tracked(&file, |_| 0).is_some());
+
+        fs::write(&file, b"[mesh]\nhouse.o3d").unwrap();
+        assert!(cache.get_tracked(&file, |_| 0).is_none());
+
+        cache.insert_tracked(&file, 0, dependencies(), HashMap::new(), Vec::new());
+        assert!(cache.get_tracked(&file, |_| 0).is_some());
+        let earlier = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options().write(true).open(&file).unwrap().set_modified(earlier).unwrap();
+        assert!(cache.get_tracked(&file, |_| 0).is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
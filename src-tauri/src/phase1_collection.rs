@@ -1,51 +1,49 @@
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::io::Read;
-use std::collections::HashSet;
-use encoding_rs::UTF_16LE;
-use encoding_rs_io::DecodeReaderBytesBuilder;
 use rayon::prelude::*;
+use crate::parse_cache::ParseCache;
+use crate::encoding::{self, CodePage};
+use crate::provenance::FoundPaths;
+use crate::types::{Dependency, DependencySource};
 
 
-/// Debug mode - set to true to log all collected paths to file
-const DEBUG: bool = true;
-
 /// Collects ALL file paths from map configuration files without categorization
-/// Returns a HashSet to eliminate duplicates
-pub fn collect_all_dependencies(map_folder: &Path) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
-    let mut all_paths = HashSet::new();
+/// Paths from known sections are tagged as exact, those from the generic scan as heuristic
+/// Unchanged map tiles are served from the parse cache instead of being re-parsed
+/// `code_page` is used for files that are neither Unicode nor valid UTF-8 (None = detect)
+pub fn collect_all_dependencies(map_folder: &Path, cache: &ParseCache, code_page: CodePage) -> Result<FoundPaths, Box<dyn std::error::Error>> {
+    let mut all_paths = FoundPaths::default();
 
     // Phase 1: Collect from all map tiles (.map files)
-    collect_from_map_tiles(map_folder, &mut all_paths)?;
+    collect_from_map_tiles(map_folder, cache, code_page, &mut all_paths)?;
 
     // Phase 1: Collect from global.cfg
-    let global_cfg = map_folder.join("global.cfg");
-    if global_cfg.exists() {
-        collect_from_global_cfg(&global_cfg, &mut all_paths)?;
+    if map_folder.join("global.cfg").exists() {
+        collect_from_global_cfg(map_folder, code_page, &mut all_paths)?;
     }
 
     // Phase 1: Collect from ailists.cfg
     let ailists_cfg = map_folder.join("ailists.cfg");
     if ailists_cfg.exists() {
-        collect_from_ailists_cfg(&ailists_cfg, &mut all_paths)?;
+        collect_from_ailists_cfg(&ailists_cfg, code_page, &mut all_paths)?;
     }
 
     // Phase 1: Collect from parklist_p.txt
     let parklist = map_folder.join("parklist_p.txt");
     if parklist.exists() {
-        collect_from_parklist(&parklist, &mut all_paths)?;
+        collect_from_parklist(&parklist, code_page, &mut all_paths)?;
     }
 
     // Phase 1: Collect from humans.txt
     let humans_txt = map_folder.join("humans.txt");
     if humans_txt.exists() {
-        collect_from_text_file(&humans_txt, &mut all_paths)?;
+        collect_from_text_file(&humans_txt, code_page, &mut all_paths)?;
     }
 
     // Phase 1: Collect from drivers.txt
     let drivers_txt = map_folder.join("drivers.txt");
     if drivers_txt.exists() {
-        collect_from_text_file(&drivers_txt, &mut all_paths)?;
+        collect_from_text_file(&drivers_txt, code_page, &mut all_paths)?;
     }
 
     // Scan any other .txt files in map folder recursively (excluding the ones already processed)
@@ -59,12 +57,13 @@ pub fn collect_all_dependencies(map_folder: &Path) -> Result<HashSet<String>, Bo
         .collect();
 
     // Process txt files in parallel
-    let txt_results: Vec<HashSet<String>> = txt_files.par_iter()
+    let txt_results: Vec<FoundPaths> = txt_files.par_iter()
         .map(|p| {
-            let mut local_paths = HashSet::new();
+            let mut local_paths = FoundPaths::default();
             let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+            // debug_collected_paths.txt may still be left behind by older versions
             if name != "parklist_p.txt" && name != "humans.txt" && name != "drivers.txt" && name != "debug_collected_paths.txt" {
-                let _ = collect_from_text_file(&p, &mut local_paths);
+                let _ = collect_from_text_file(p, code_page, &mut local_paths);
             }
             local_paths
         })
@@ -72,12 +71,7 @@ pub fn collect_all_dependencies(map_folder: &Path) -> Result<HashSet<String>, Bo
 
     // Merge results
     for res in txt_results {
-        all_paths.extend(res);
-    }
-
-    // Debug: Log all collected paths
-    if DEBUG {
-        log_to_file(&all_paths, map_folder)?;
+        all_paths.merge(res);
     }
 
     Ok(all_paths)
@@ -89,7 +83,9 @@ pub fn collect_all_dependencies(map_folder: &Path) -> Result<HashSet<String>, Bo
 
 fn collect_from_map_tiles(
     map_folder: &Path,
-    all_paths: &mut HashSet<String>,
+    cache: &ParseCache,
+    code_page: CodePage,
+    all_paths: &mut FoundPaths,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut map_files = Vec::new();
 
@@ -119,11 +115,21 @@ fn collect_from_map_tiles(
     }
 
     // Process map files in parallel
-    let results: Vec<Result<HashSet<String>, String>> = map_files.par_iter()
+    let results: Vec<Result<FoundPaths, String>> = map_files.par_iter()
         .map(|path| {
-            let mut local_paths = HashSet::new();
-            match collect_from_single_map_file(path, &mut local_paths) {
-                Ok(_) => Ok(local_paths),
+            // Tiles only depend on their own content (and how it is decoded),
+            // so a cached result is valid until the file or the code page changes
+            let stamp = encoding::code_page_stamp(code_page);
+            if let Some((cached, sources)) = cache.get_tracked(path, |_| stamp) {
+                return Ok(FoundPaths::from_tagged(cached, sources));
+            }
+            let mut local_paths = FoundPaths::default();
+            match collect_from_single_map_file(path, code_page, &mut local_paths) {
+                Ok(_) => {
+                    let (deps, sources) = local_paths.to_tagged();
+                    cache.insert_tracked(path, stamp, deps, sources, Vec::new());
+                    Ok(local_paths)
+                }
                 Err(e) => Err(format!("Error processing {:?}: {}", path, e))
             }
         })
@@ -132,8 +138,8 @@ fn collect_from_map_tiles(
     // Merge results
     for res in results {
         match res {
-            Ok(paths) => all_paths.extend(paths),
-            Err(e) => eprintln!("{}", e), // Log error but continue
+            Ok(paths) => all_paths.merge(paths),
+            Err(e) => log_warn!("{}", e), // Log error but continue
         }
     }
 
@@ -142,32 +148,11 @@ fn collect_from_map_tiles(
 
 fn collect_from_single_map_file(
     map_file: &Path,
-    all_paths: &mut HashSet<String>,
+    code_page: CodePage,
+    all_paths: &mut FoundPaths,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Map files typically use UTF-16LE encoding, but we try robustly
-    let bytes = fs::read(map_file)?;
-    
-    let content = if bytes.starts_with(&[0xFF, 0xFE]) {
-        // UTF-16LE with BOM (most common for .map files)
-        let mut decoder = DecodeReaderBytesBuilder::new()
-            .encoding(Some(UTF_16LE))
-            .build(std::io::Cursor::new(&bytes));
-        let mut s = String::new();
-        match decoder.read_to_string(&mut s) {
-            Ok(_) => s,
-            Err(_) => {
-                // Fallback to lossy decoding
-                let (cow, _, _) = encoding_rs::WINDOWS_1252.decode(&bytes);
-                cow.into_owned()
-            }
-        }
-    } else if let Ok(s) = String::from_utf8(bytes.clone()) {
-        s
-    } else {
-        // Fallback to Windows-1252
-        let (cow, _, _) = encoding_rs::WINDOWS_1252.decode(&bytes);
-        cow.into_owned()
-    };
+    // Map files typically use UTF-16LE encoding; decode_text handles all variants
+    let content = encoding::read_text_file(map_file, code_page)?;
     
     let mut lines = content.lines().peekable();
 
@@ -180,7 +165,7 @@ fn collect_from_single_map_file(
             if let Some(path_line) = lines.next() {
                 let path = path_line.trim().to_string();
                 if !path.is_empty() {
-                    try_insert_path(all_paths, &path);
+                    try_insert_path(all_paths, &path, DependencySource::Section);
                 }
             }
         }
@@ -191,7 +176,7 @@ fn collect_from_single_map_file(
             if let Some(path_line) = lines.next() {
                 let path = path_line.trim().to_string();
                 if !path.is_empty() {
-                    try_insert_path(all_paths, &path);
+                    try_insert_path(all_paths, &path, DependencySource::Section);
                 }
             }
         }
@@ -203,34 +188,18 @@ fn collect_from_single_map_file(
     Ok(())
 }
 
+/// Read the global.cfg of a map folder as text
+pub fn read_global_cfg(map_folder: &Path, code_page: CodePage) -> std::io::Result<String> {
+    // global.cfg typically uses UTF-16LE encoding; decode_text handles all variants
+    encoding::read_text_file(&map_folder.join("global.cfg"), code_page)
+}
+
 fn collect_from_global_cfg(
-    global_cfg: &Path,
-    all_paths: &mut HashSet<String>,
+    map_folder: &Path,
+    code_page: CodePage,
+    all_paths: &mut FoundPaths,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // global.cfg typically uses UTF-16LE encoding, but we try robustly
-    let bytes = fs::read(global_cfg)?;
-    
-    let content = if bytes.starts_with(&[0xFF, 0xFE]) {
-        // UTF-16LE with BOM (most common for global.cfg)
-        let mut decoder = DecodeReaderBytesBuilder::new()
-            .encoding(Some(UTF_16LE))
-            .build(std::io::Cursor::new(&bytes));
-        let mut s = String::new();
-        match decoder.read_to_string(&mut s) {
-            Ok(_) => s,
-            Err(_) => {
-                // Fallback to lossy decoding
-                let (cow, _, _) = encoding_rs::WINDOWS_1252.decode(&bytes);
-                cow.into_owned()
-            }
-        }
-    } else if let Ok(s) = String::from_utf8(bytes.clone()) {
-        s
-    } else {
-        // Fallback to Windows-1252
-        let (cow, _, _) = encoding_rs::WINDOWS_1252.decode(&bytes);
-        cow.into_owned()
-    };
+    let content = read_global_cfg(map_folder, code_page)?;
     
     let mut lines = content.lines().peekable();
 
@@ -242,7 +211,7 @@ fn collect_from_global_cfg(
             if let Some(path_line) = lines.next() {
                 let path = path_line.trim().to_string();
                 if !path.is_empty() {
-                    try_insert_path(all_paths, &path);
+                    try_insert_path(all_paths, &path, DependencySource::Section);
                 }
             }
         }
@@ -252,7 +221,7 @@ fn collect_from_global_cfg(
             if let Some(path_line) = lines.next() {
                 let path = path_line.trim().to_string();
                 if !path.is_empty() {
-                    try_insert_path(all_paths, &path);
+                    try_insert_path(all_paths, &path, DependencySource::Section);
                 }
             }
         }
@@ -262,7 +231,7 @@ fn collect_from_global_cfg(
             if let Some(path_line) = lines.next() {
                 let path = path_line.trim().to_string();
                 if !path.is_empty() {
-                    try_insert_path(all_paths, &path);
+                    try_insert_path(all_paths, &path, DependencySource::Section);
                 }
             }
         }
@@ -272,7 +241,7 @@ fn collect_from_global_cfg(
             if let Some(path_line) = lines.next() {
                 let path = path_line.trim().to_string();
                 if !path.is_empty() {
-                    try_insert_path(all_paths, &path);
+                    try_insert_path(all_paths, &path, DependencySource::Section);
                 }
             }
         }
@@ -282,7 +251,7 @@ fn collect_from_global_cfg(
             if let Some(path_line) = lines.next() {
                 let path = path_line.trim().to_string();
                 if !path.is_empty() {
-                    try_insert_path(all_paths, &path);
+                    try_insert_path(all_paths, &path, DependencySource::Section);
                 }
             }
         }
@@ -296,9 +265,10 @@ fn collect_from_global_cfg(
 
 fn collect_from_ailists_cfg(
     ailists_cfg: &Path,
-    all_paths: &mut HashSet<String>,
+    code_page: CodePage,
+    all_paths: &mut FoundPaths,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let content = read_file_robust(ailists_cfg)?;
+    let content = encoding::read_text_file(ailists_cfg, code_page)?;
     let mut lines = content.lines().peekable();
     let mut in_depot_typgroup = false;
 
@@ -314,7 +284,7 @@ fn collect_from_ailists_cfg(
             if trimmed.ends_with(".bus") || trimmed.ends_with(".ovh") || 
                trimmed.ends_with(".zug") || trimmed.ends_with(".sco") {
                 let path = trimmed.to_string();
-                try_insert_path(all_paths, &path);
+                try_insert_path(all_paths, &path, DependencySource::Section);
                 in_depot_typgroup = false;
             }
         } else {
@@ -323,7 +293,7 @@ fn collect_from_ailists_cfg(
                 if first_part.ends_with(".bus") || first_part.ends_with(".ovh") || 
                    first_part.ends_with(".zug") || first_part.ends_with(".sco") {
                     let path = first_part.to_string();
-                    try_insert_path(all_paths, &path);
+                    try_insert_path(all_paths, &path, DependencySource::Section);
                 }
             }
         }
@@ -337,9 +307,10 @@ fn collect_from_ailists_cfg(
 
 fn collect_from_parklist(
     parklist: &Path,
-    all_paths: &mut HashSet<String>,
+    code_page: CodePage,
+    all_paths: &mut FoundPaths,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let content = read_file_robust(parklist)?;
+    let content = encoding::read_text_file(parklist, code_page)?;
 
     for line in content.lines() {
         let trimmed = line.trim();
@@ -353,7 +324,7 @@ fn collect_from_parklist(
            trimmed.ends_with(".bus") || trimmed.ends_with(".zug") ||
            trimmed.ends_with(".sli") || trimmed.ends_with(".hum") {
             let path = trimmed.to_string();
-            try_insert_path(all_paths, &path);
+            try_insert_path(all_paths, &path, DependencySource::Section);
         }
     }
 
@@ -363,34 +334,12 @@ fn collect_from_parklist(
     Ok(())
 }
 
-/// Robustly read a file with various encodings: UTF-8, UTF-16LE, Windows-1252
-fn read_file_robust(file_path: &Path) -> Result<String, Box<dyn std::error::Error>> {
-    let bytes = fs::read(file_path)?;
-
-    let content = if bytes.starts_with(&[0xFF, 0xFE]) {
-        // UTF-16LE with BOM
-        let mut decoder = DecodeReaderBytesBuilder::new()
-            .encoding(Some(UTF_16LE))
-            .build(std::io::Cursor::new(bytes));
-        let mut s = String::new();
-        decoder.read_to_string(&mut s)?;
-        s
-    } else if let Ok(s) = String::from_utf8(bytes.clone()) {
-        s
-    } else {
-        // Fallback to Windows-1252 (common for OMSI text files)
-        let (cow, _enc_used, _had_errors) = encoding_rs::WINDOWS_1252.decode(&bytes);
-        cow.into_owned()
-    };
-
-    Ok(content)
-}
-
 fn collect_from_text_file(
     text_file: &Path,
-    all_paths: &mut HashSet<String>,
+    code_page: CodePage,
+    all_paths: &mut FoundPaths,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let content = read_file_robust(text_file)?;
+    let content = encoding::read_text_file(text_file, code_page)?;
     extract_paths_generic(&content, all_paths);
     Ok(())
 }
@@ -399,7 +348,7 @@ fn collect_from_text_file(
 // GENERIC PATH SCANNER
 // ============================================================================
 
-fn extract_paths_generic(content: &str, all_paths: &mut HashSet<String>) {
+fn extract_paths_generic(content: &str, all_paths: &mut FoundPaths) {
     // Known file extensions (lowercase)
     const EXTS: &[&str] = &[
         "sco", "ovh", "bus", "zug", "sli", "hum", "wav", "jpg", "jpeg", "bmp", "dds", "png", "tga", "cfg", "osc", "x", "o3d", "surf", "map", "otp", "txt",
@@ -479,7 +428,7 @@ fn extract_paths_generic(content: &str, all_paths: &mut HashSet<String>) {
 
                     if ends_with_ext && (has_sep || starts_with_known) {
                         let normalized = cand.replace('/', "\\");
-                        try_insert_path(all_paths, &normalized);
+                        try_insert_path(all_paths, &normalized, DependencySource::PathScan);
                     }
                 }
             }
@@ -526,10 +475,10 @@ fn normalize_path(raw: &str) -> Option<String> {
 }
 
 /// Centralized insertion with validation
-fn try_insert_path(all_paths: &mut HashSet<String>, raw: &str) {
+fn try_insert_path(all_paths: &mut FoundPaths, raw: &str, source: DependencySource) {
     if let Some(p) = normalize_path(raw) {
         if is_plausible_path(&p) {
-            all_paths.insert(p);
+            all_paths.insert(Dependency::File(p), source);
         }
     }
 }
@@ -560,63 +509,6 @@ fn is_plausible_path(normalized: &str) -> bool {
     false
 }
 
-// ============================================================================
-// DEBUG LOGGING
-// ============================================================================
-
-fn log_to_file(
-    all_paths: &HashSet<String>,
-    map_folder: &Path,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let log_path = map_folder.join("debug_collected_paths.txt");
-    
-    // Group by extension for readability and summary
-    let mut grouped: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
-    for path in all_paths {
-        let ext = path.rsplit('.').next().unwrap_or("unknown").to_lowercase();
-        grouped.entry(ext).or_insert_with(Vec::new).push(path.clone());
-    }
-
-    // Prepare summary counts sorted by extension
-    let mut extensions: Vec<_> = grouped.keys().cloned().collect();
-    extensions.sort();
-
-    let mut summary_lines = String::new();
-    summary_lines.push_str("Summary by extension:\n");
-    for ext in &extensions {
-        if let Some(paths) = grouped.get(ext) {
-            summary_lines.push_str(&format!("  .{}: {}\n", ext, paths.len()));
-        }
-    }
-
-    let mut log_content = String::new();
-    log_content.push_str(&format!("=== PHASE 1: Collected {} file paths ===\n", all_paths.len()));
-    log_content.push_str(&format!("Map folder: {}\n", map_folder.display()));
-    log_content.push_str("Timestamp: [Generated on extraction]\n\n");
-    // Add summary at the top
-    log_content.push_str(&summary_lines);
-    log_content.push('\n');
-
-    // Detailed listing grouped by extension
-    for ext in extensions {
-        if let Some(paths) = grouped.get(&ext) {
-            log_content.push_str(&format!("\n.{} files ({}):\n", ext, paths.len()));
-            log_content.push_str(&"─".repeat(60));
-            log_content.push('\n');
-            for path in paths {
-                log_content.push_str(&format!("  {}\n", path));
-            }
-        }
-    }
-
-    log_content.push_str(&format!("\n{}\n", "=".repeat(60)));
-    log_content.push_str(&format!("Total: {} paths collected\n", all_paths.len()));
-
-    fs::write(&log_path, log_content)?;
-
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
     #[test]
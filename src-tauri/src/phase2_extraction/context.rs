@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+use crate::file_index::{fnv1a, normalize_key, FileIndex, FNV_OFFSET};
+use crate::parse_cache::ParseCache;
+use crate::encoding::{self, CodePages};
+use crate::phase2_extraction::extractor::{Extraction, ExtractorRegistry};
+use crate::phase2_extraction::rules::RuleSet;
+use crate::provenance::{FoundPaths, Provenance};
+use crate::types::DependencySource;
+
+/// Shared state for a single dependency resolution run
+pub struct ExtractionContext {
+    pub index: FileIndex,
+    pub cache: ParseCache,
+    /// Code pages for legacy text files, per folder
+    pub code_pages: CodePages,
+    /// Heuristic sources whose results are left out
+    pub disabled_sources: Vec<DependencySource>,
+    /// How every dependency of the run was found
+    pub provenance: Provenance,
+    /// How the paths of each text format are read
+    pub rules: RuleSet,
+    /// Extractor for each file extension; further extractors can be registered before the run
+    pub extractors: ExtractorRegistry,
+    /// Fingerprint of the global Texture folder, searched by most extractors
+    texture_stamp: u64,
+}
+
+impl ExtractionContext {
+    /// Create a context for the given OMSI root, indexing its asset folders
+    pub fn new(omsi_root: &Path, cache: ParseCache, code_pages: CodePages) -> Self {
+        Self::with_fallback_roots(omsi_root, &[], cache, code_pages)
+    }
+
+    /// Create a context that also searches `fallback_roots`, in order, for files missing in the OMSI root
+    pub fn with_fallback_roots(omsi_root: &Path, fallback_roots: &[PathBuf], cache: ParseCache, code_pages: CodePages) -> Self {
+        let index = FileIndex::build_with_fallbacks(omsi_root, fallback_roots);
+        let texture_stamp = index.subtree_stamp(Path::new("Texture"));
+        let rules = RuleSet::load_default();
+        let extractors = ExtractorRegistry::builtin(&rules);
+        ExtractionContext {
+            index,
+            cache,
+            code_pages,
+            disabled_sources: Vec::new(),
+            provenance: Provenance::default(),
+            rules,
+            extractors,
+            texture_stamp,
+        }
+    }
+
+    /// Whether results of `source` are used
+    pub fn uses(&self, source: DependencySource) -> bool {
+        !self.disabled_sources.contains(&source)
+    }
+
+    // Extractors fall back to broader lookups when a heuristic is disabled
+    fn sources_stamp(&self) -> u64 {
+        if self.disabled_sources.is_empty() {
+            return 0;
+        }
+        let mut disabled = self.disabled_sources.clone();
+        disabled.sort();
+        fnv1a(FNV_OFFSET, format!("{:?}", disabled).as_bytes())
+    }
+
+    /// Run an extractor through the parse cache.
+    ///
+    /// The result of texture and mesh lookups depends on the files next to the parsed
+    /// file, so cached results are also tied to the file's folder, the global Texture folder
+    /// and every other folder the extraction looked in. Results that looked outside the
+    /// indexed folders are not cached.
+    /// Diagnostics are only reported when the file is actually parsed.
+    pub fn cached_dependencies<F>(&self, rel_path: &str, extract: F) -> Option<Extraction>
+    where
+        F: FnOnce() -> Option<Extraction>,
+    {
+        let full_path = self.index.full_path(rel_path);
+        let folder = normalize_key(&Path::new(rel_path).parent().unwrap_or(Path::new("")).to_string_lossy());
+        let base_stamp = chain(&[
+            self.index.subtree_stamp(Path::new(&folder)),
+            self.texture_stamp,
+            encoding::code_page_stamp(self.code_pages.for_path(rel_path)),
+            self.sources_stamp(),
+            self.rules.stamp(),
+        ]);
+        let stamp = |lookups: &[String]| lookups.iter().fold(base_stamp, |stamp, dir| chain(&[
+            stamp,
+            self.index.subtree_stamp(Path::new(dir)),
+            encoding::code_page_stamp(self.code_pages.for_path(dir)),
+        ]));
+
+        if let Some((deps, sources)) = self.cache.get_tracked(&full_path, stamp) {
+            return Some(FoundPaths::from_tagged(deps, sources).into());
+        }
+
+        let extraction = extract()?;
+        let mut lookups = Vec::new();
+        for dir in &extraction.lookups {
+            let key = normalize_key(dir);
+            if within(&key, &folder) || within(&key, "texture") {
+                continue;
+            }
+            if !self.index.tracks(&key) {
+                return Some(extraction);
+            }
+            lookups.push(key);
+        }
+        lookups.sort();
+        lookups.dedup();
+
+        let (deps, sources) = extraction.found.to_tagged();
+        self.cache.insert_tracked(&full_path, stamp(&lookups), deps, sources, lookups);
+        Some(extraction)
+    }
+}
+
+/// Combine stamps so that equal parts do not cancel out
+fn chain(stamps: &[u64]) -> u64 {
+    stamps.iter().fold(FNV_OFFSET, |hash, stamp| fnv1a(hash, &stamp.to_le_bytes()))
+}
+
+/// Whether the index key `key` is `folder` or lies below it
+fn within(key: &str, folder: &str) -> bool {
+    key.strip_prefix(folder).is_some_and(|rest| rest.is_empty() || rest.starts_with('\\'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::test_utils::test_dir;
+
+    // Run the sceneryobject extractor through the cache; true when the file was parsed again
+    fn parses(root: &Path, cache: &mut ParseCache) -> bool {
+        let mut ctx = ExtractionContext::new(root, std::mem::take(cache), CodePages::default());
+        let rel_path = "Sceneryobjects\\Author\\Obj\\obj.sco";
+        let mut parsed = false;
+        let extractor = ctx.extractors.for_path(rel_path).unwrap();
+        ctx.cached_dependencies(rel_path, || {
+            parsed = true;
+            extractor.extract(rel_path, &ctx)
+        }).unwrap();
+        *cache = std::mem::take(&mut ctx.cache);
+        parsed
+    }
+
+    #[test]
+    fn cached_results_follow_folders_outside_their_own() {
+        let root = test_dir("context_lookups");
+        fs::create_dir_all(root.join("Sceneryobjects/Author/Obj")).unwrap();
+        fs::create_dir_all(root.join("Sceneryobjects/Shared")).unwrap();
+        fs::write(root.join("Sceneryobjects/Author/Obj/obj.sco"), "[mesh]\n..\\..\\Shared\\shared.o3d\n").unwrap();
+
+        let mut cache = ParseCache::default();
+        assert!(parses(&root, &mut cache));
+        assert!(!parses(&root, &mut cache));
+
+        // The mesh appears next to the object's folder
+        fs::write(root.join("Sceneryobjects/Shared/shared.o3d"), b"").unwrap();
+        assert!(parses(&root, &mut cache));
+        assert!(!parses(&root, &mut cache));
+
+        fs::write(root.join("Sceneryobjects/Shared/shared.o3d"), b"changed").unwrap();
+        assert!(parses(&root, &mut cache));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn equal_stamps_do_not_cancel_out() {
+        assert_ne!(chain(&[5, 5]), chain(&[7, 7]));
+        assert_ne!(chain(&[1, 2]), chain(&[2, 1]));
+    }
+}
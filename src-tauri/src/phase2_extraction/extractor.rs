@@ -0,0 +1,98 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use crate::phase2_extraction::{bus, rules, sco};
+use crate::phase2_extraction::context::ExtractionContext;
+use crate::phase2_extraction::rules::RuleSet;
+use crate::provenance::FoundPaths;
+
+/// Output of an extractor for one file
+#[derive(Debug, Default)]
+pub struct Extraction {
+    /// Dependencies of the file, tagged with how they were found
+    pub found: FoundPaths,
+    /// Problems found in the file (missing references, invalid paths), logged by the caller
+    pub diagnostics: Vec<String>,
+    /// Folders searched besides the folder of the file, relative to the OMSI root.
+    /// Cached results are tied to their contents.
+    pub lookups: HashSet<String>,
+}
+
+impl Extraction {
+    pub fn warn(&mut self, message: String) {
+        self.diagnostics.push(message);
+    }
+
+    pub fn looked_in(&mut self, folder: &str) {
+        self.lookups.insert(folder.to_string());
+    }
+}
+
+impl From<FoundPaths> for Extraction {
+    fn from(found: FoundPaths) -> Extraction {
+        Extraction { found, ..Extraction::default() }
+    }
+}
+
+/// Reads the dependencies of one kind of file
+pub trait Extractor: Send + Sync {
+    /// Extract the dependencies of `rel_path` (relative to the OMSI root, as found in the index).
+    /// Returns None when the file cannot be read.
+    fn extract(&self, rel_path: &str, ctx: &ExtractionContext) -> Option<Extraction>;
+
+    /// Whether results can be kept in the parse cache.
+    /// Only safe when the result depends on nothing but the file, its folder, the global Texture
+    /// folder and the folders recorded with `Extraction::looked_in`.
+    fn cacheable(&self) -> bool {
+        false
+    }
+}
+
+/// Extractors by file extension
+#[derive(Default, Clone)]
+pub struct ExtractorRegistry {
+    extractors: HashMap<String, Arc<dyn Extractor>>,
+}
+
+impl ExtractorRegistry {
+    /// The built-in extractors: one per file extension with extraction rules, plus sceneryobjects and buses
+    pub fn builtin(rules: &RuleSet) -> ExtractorRegistry {
+        let mut registry = ExtractorRegistry::default();
+        for format in rules.extensions() {
+            // Spline textures are looked up next to the spline and in the global Texture folder
+            let cacheable = format == "sli";
+            registry.register(format, Arc::new(rules::RuleExtractor { cacheable }));
+        }
+        registry.register("sco", Arc::new(sco::ScoExtractor));
+        registry.register("bus", Arc::new(bus::BusExtractor));
+        registry
+    }
+
+    /// Handle files with `extension` (without the dot, case-insensitive), replacing any previous extractor
+    pub fn register(&mut self, extension: &str, extractor: Arc<dyn Extractor>) {
+        self.extractors.insert(extension.to_lowercase(), extractor);
+    }
+
+    /// Extractor for a file, by its extension
+    pub fn for_path(&self, rel_path: &str) -> Option<&dyn Extractor> {
+        let file_name = rel_path.rsplit(['\\', '/']).next().unwrap_or(rel_path);
+        let extension = file_name.rsplit_once('.')?.1.to_lowercase();
+        self.extractors.get(&extension).map(|e| e.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_extractor_by_extension() {
+        let registry = ExtractorRegistry::builtin(&RuleSet::builtin());
+        assert!(registry.for_path("Sceneryobjects\\A\\obj.SCO").is_some_and(|e| e.cacheable()));
+        assert!(registry.for_path("Vehicles\\Bus\\bus.ovh").is_some_and(|e| !e.cacheable()));
+        assert!(registry.for_path("Texture\\a.jpg").is_none());
+        // Sound and human configs are only read from the files that name them
+        assert!(registry.for_path("Vehicles\\Bus\\sound\\sound.cfg").is_none());
+        assert!(registry.for_path("Humans\\A\\model.cfg").is_none());
+        assert!(registry.for_path("maps\\TestMap\\readme").is_none());
+    }
+}
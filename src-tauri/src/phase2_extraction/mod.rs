@@ -1,10 +1,8 @@
 pub mod bus;
-pub mod cfg;
-pub mod hum;
+pub mod context;
+pub mod extractor;
 pub mod o3d;
-pub mod ovh;
+pub mod rules;
 pub mod sco;
-pub mod sli;
 pub mod utils;
 pub mod x;
-pub mod zug;
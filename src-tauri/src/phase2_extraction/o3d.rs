@@ -1,16 +1,25 @@
-use std::path::Path;
+use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
 use crate::phase2_extraction::utils;
+use crate::phase2_extraction::context::ExtractionContext;
+use crate::types::{Dependency, DependencySource};
 
-/// Extract texture references from .o3d binary file
-pub fn extract_o3d_textures(o3d_path: &str, omsi_root: &Path) -> Option<Vec<String>> {
-    let full_o3d_path = omsi_root.join(o3d_path);
+/// Extract texture references from .o3d binary file.
+/// The names are scanned from the file bytes, so they are tagged as a binary scan.
+pub fn extract_o3d_textures(o3d_path: &str, ctx: &ExtractionContext) -> Option<(Vec<String>, DependencySource)> {
+    let full_o3d_path = ctx.index.full_path(o3d_path);
     
-    if !full_o3d_path.exists() {
+    if !ctx.index.is_file(o3d_path) {
         return None;
     }
     
+    // Texture names (cached as file entries) only depend on the file content, so they stay valid until it changes
+    if let Some((cached, _)) = ctx.cache.get_tracked(&full_o3d_path, |_| 0) {
+        let textures: Vec<String> = cached.iter().map(|d| d.path().to_string()).collect();
+        return if textures.is_empty() { None } else { Some((textures, DependencySource::BinaryScan)) };
+    }
+    
     // Read binary file
     let mut file = match fs::File::open(&full_o3d_path) {
         Ok(f) => f,
@@ -22,5 +31,8 @@ pub fn extract_o3d_textures(o3d_path: &str, omsi_root: &Path) -> Option<Vec<Stri
         return None;
     }
     
-    utils::extract_textures_from_binary(&buffer)
+    let textures = utils::extract_textures_from_binary(&buffer);
+    let names = textures.iter().flatten().map(|name| Dependency::File(name.clone())).collect();
+    ctx.cache.insert_tracked(&full_o3d_path, 0, names, HashMap::new(), Vec::new());
+    textures.map(|t| (t, DependencySource::BinaryScan))
 }
@@ -0,0 +1,557 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::file_index::{fnv1a, FNV_OFFSET};
+use crate::phase2_extraction::{o3d, utils, x};
+use crate::phase2_extraction::context::ExtractionContext;
+use crate::phase2_extraction::extractor::{Extraction, Extractor};
+use crate::types::{Dependency, DependencySource};
+use crate::utils::app_data_dir;
+
+/// Rules for the sections of the OMSI file formats the bundler knows
+const DEFAULT_RULES: &str = include_str!("default_rules.json");
+
+/// User rules in the app data folder, checked before the default rules
+const USER_RULES_FILE: &str = "extraction_rules.json";
+
+/// Depth limit for rules that parse found files with further rules
+const MAX_RECURSION: usize = 4;
+
+/// What the path lines of a section stand for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleKind {
+    /// A file, taken from the first search folder that has it
+    #[default]
+    File,
+    /// A texture with all its variants (other extensions, night/winter folders, .cfg and .surf files)
+    Texture,
+    /// A mesh file; the textures named inside it are added too
+    Mesh,
+    /// A folder whose files are added
+    Folder,
+    /// The whole folder of the parsed file; the path line itself is not used
+    OwnFolder,
+    /// The whole folder of the referenced file (relative to the OMSI root)
+    ParentFolder,
+    /// Nothing; used to turn off a default rule
+    Ignore,
+}
+
+/// How to read the paths of a section of a file format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionRule {
+    /// File extension the rule applies to (`sco`), or a format listed in `recurse_only`
+    pub format: String,
+    /// Section tags (`[mesh]`), case-insensitive. Without tags every line is a candidate path.
+    #[serde(default)]
+    pub sections: Vec<String>,
+    /// Lines after the tag that are skipped (e.g. an ID or a variable name)
+    #[serde(default)]
+    pub skip: usize,
+    /// Number of path lines after the skipped ones
+    #[serde(default = "one_line")]
+    pub lines: usize,
+    /// Read the number of path lines from the line after the skipped ones instead
+    #[serde(default)]
+    pub counted: bool,
+    /// Accepted path extensions, without the dot; any when empty
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Folders tried in order, relative to the folder of the parsed file (`model`, `""` for the folder itself)
+    /// or, with a leading slash, to the OMSI root (`/`, `/Texture`). Paths starting with `..` are always
+    /// relative to the folder of the parsed file.
+    #[serde(default = "own_folder")]
+    pub search: Vec<String>,
+    #[serde(default)]
+    pub kind: RuleKind,
+    /// Search folder a file that is not found is kept in, so it is reported missing; left out when unset
+    #[serde(default)]
+    pub keep_missing: Option<String>,
+    /// Cut `;` and `#` comments off the path lines
+    #[serde(default)]
+    pub strip_comments: bool,
+    /// Also parse found files with the rules of this format
+    #[serde(default)]
+    pub recurse: Option<String>,
+    /// Meshes only: folders (like `search`) added whole when the mesh names no texture
+    #[serde(default)]
+    pub fallback_folders: Vec<String>,
+}
+
+fn one_line() -> usize {
+    1
+}
+
+fn own_folder() -> Vec<String> {
+    vec![String::new()]
+}
+
+impl ExtractionRule {
+    /// The path on a line, if the rule accepts it
+    fn path_of(&self, line: &str) -> Option<String> {
+        let mut path = line.trim();
+        if self.strip_comments {
+            path = path.split([';', '#']).next().unwrap_or("").trim();
+        }
+        let lower = path.to_lowercase();
+        let accepted = self.extensions.is_empty()
+            || self.extensions.iter().any(|ext| lower.ends_with(&format!(".{}", ext.to_lowercase())));
+        (!path.is_empty() && accepted).then(|| path.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct RuleFile {
+    rules: Vec<ExtractionRule>,
+    /// Formats only parsed through `recurse` (like `sound` for sound configs), never picked by file extension
+    #[serde(default)]
+    recurse_only: Vec<String>,
+}
+
+/// Extraction rules by format
+pub struct RuleSet {
+    formats: HashMap<String, Vec<ExtractionRule>>,
+    /// Formats without a file extension of their own, lowercase
+    recurse_only: HashSet<String>,
+    /// Fingerprint of the user rules
+    stamp: u64,
+}
+
+impl RuleSet {
+    fn new(files: Vec<RuleFile>, stamp: u64) -> RuleSet {
+        let mut formats: HashMap<String, Vec<ExtractionRule>> = HashMap::new();
+        let mut recurse_only = HashSet::new();
+        for file in files {
+            for rule in file.rules {
+                formats.entry(rule.format.to_lowercase()).or_default().push(rule);
+            }
+            recurse_only.extend(file.recurse_only.iter().map(|format| format.to_lowercase()));
+        }
+        RuleSet { formats, recurse_only, stamp }
+    }
+
+    fn default_rules() -> RuleFile {
+        serde_json::from_str(DEFAULT_RULES).expect("default extraction rules are valid")
+    }
+
+    /// The default rules only
+    pub fn builtin() -> RuleSet {
+        RuleSet::new(vec![RuleSet::default_rules()], 0)
+    }
+
+    /// The default rules, extended by the user rules in the app data folder
+    pub fn load_default() -> RuleSet {
+        let path = user_rules_path();
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log_warn!("Failed to read extraction rules {:?}: {}", path, e);
+                }
+                return RuleSet::builtin();
+            }
+        };
+        match serde_json::from_slice::<RuleFile>(&bytes) {
+            Ok(file) => {
+                log_info!("Loaded {} extraction rules from {:?}", file.rules.len(), path);
+                RuleSet::new(vec![file, RuleSet::default_rules()], fnv1a(FNV_OFFSET, &bytes))
+            }
+            Err(e) => {
+                log_warn!("Extraction rules {:?} ignored: {}", path, e);
+                RuleSet::builtin()
+            }
+        }
+    }
+
+    /// Formats that have rules
+    pub fn formats(&self) -> impl Iterator<Item = &str> {
+        self.formats.keys().map(String::as_str)
+    }
+
+    /// Formats that are file extensions, i.e. not only reached through `recurse`
+    pub fn extensions(&self) -> impl Iterator<Item = &str> {
+        self.formats().filter(|format| !self.recurse_only.contains(*format))
+    }
+
+    fn rules(&self, format: &str) -> &[ExtractionRule] {
+        self.formats.get(format).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Fingerprint of the user rules; zero without any
+    pub fn stamp(&self) -> u64 {
+        self.stamp
+    }
+}
+
+/// Location of the user rules
+pub fn user_rules_path() -> PathBuf {
+    app_data_dir().join(USER_RULES_FILE)
+}
+
+/// Join OMSI-style path parts with backslashes, resolving `.` and `..` lexically
+fn join(parts: &[&str]) -> String {
+    let mut joined: Vec<&str> = Vec::new();
+    for part in parts.iter().flat_map(|p| p.split(['\\', '/'])) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                joined.pop();
+            }
+            other => joined.push(other),
+        }
+    }
+    joined.join("\\")
+}
+
+/// Path of `value` in a search folder (see `ExtractionRule::search`)
+fn resolve(base: &str, folder: &str, value: &str) -> String {
+    if value.starts_with("..") {
+        join(&[folder, value])
+    } else if base.starts_with(['/', '\\']) {
+        join(&[base, value])
+    } else {
+        join(&[folder, base, value])
+    }
+}
+
+fn parent(rel_path: &str) -> &str {
+    rel_path.rsplit_once(['\\', '/']).map(|(parent, _)| parent).unwrap_or("")
+}
+
+fn base_name(file_name: &str) -> &str {
+    file_name.rsplit_once('.').map(|(base, _)| base).unwrap_or(file_name)
+}
+
+/// Extractor for the formats covered by extraction rules, picked by file extension
+pub struct RuleExtractor {
+    pub cacheable: bool,
+}
+
+impl Extractor for RuleExtractor {
+    fn extract(&self, rel_path: &str, ctx: &ExtractionContext) -> Option<Extraction> {
+        let file_name = rel_path.rsplit(['\\', '/']).next().unwrap_or(rel_path);
+        let format = file_name.rsplit_once('.')?.1.to_lowercase();
+        extract_format(rel_path, &format, ctx)
+    }
+
+    fn cacheable(&self) -> bool {
+        self.cacheable
+    }
+}
+
+/// Extract the dependencies of a file with the rules of `format`.
+/// The result includes the file itself.
+pub fn extract_format(rel_path: &str, format: &str, ctx: &ExtractionContext) -> Option<Extraction> {
+    let mut out = Extraction::default();
+    apply_rules(rel_path, format, ctx, 0, &mut out)?;
+    Some(out)
+}
+
+fn apply_rules(rel_path: &str, format: &str, ctx: &ExtractionContext, depth: usize, out: &mut Extraction) -> Option<()> {
+    let full_path = ctx.index.full_path(rel_path);
+    if !ctx.index.is_file(rel_path) {
+        log_warn!("File not found: {:?}", full_path);
+        return None;
+    }
+    let content = utils::read_text_file(&full_path, ctx.code_pages.for_path(rel_path))?;
+    out.looked_in(parent(rel_path));
+    out.found.insert(Dependency::File(rel_path.to_string()), DependencySource::Section);
+
+    let rules = ctx.rules.rules(format);
+    // Null bytes are common in badly encoded files
+    let mut lines = content.lines().map(|line| line.replace('\0', ""));
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        let section = rules.iter().find(|r| r.sections.iter().any(|s| s.eq_ignore_ascii_case(trimmed)));
+        if let Some(rule) = section {
+            for _ in 0..rule.skip {
+                lines.next();
+            }
+            let count = if rule.counted {
+                lines.next().and_then(|l| l.trim().parse().ok()).unwrap_or(0)
+            } else {
+                rule.lines
+            };
+            for _ in 0..count {
+                let Some(line) = lines.next() else { break };
+                if let Some(path) = rule.path_of(&line) {
+                    apply_rule(rule, &path, rel_path, ctx, depth, out);
+                }
+            }
+        } else if let Some((rule, path)) = rules.iter()
+            .filter(|r| r.sections.is_empty())
+            .find_map(|r| r.path_of(trimmed).map(|path| (r, path)))
+        {
+            apply_rule(rule, &path, rel_path, ctx, depth, out);
+        }
+    }
+    Some(())
+}
+
+fn apply_rule(rule: &ExtractionRule, value: &str, rel_path: &str, ctx: &ExtractionContext, depth: usize, out: &mut Extraction) {
+    let folder = parent(rel_path);
+    match rule.kind {
+        RuleKind::Ignore => {}
+        RuleKind::OwnFolder => {
+            if !folder.is_empty() {
+                out.found.insert(Dependency::Folder(folder.to_string()), DependencySource::Section);
+            }
+        }
+        RuleKind::ParentFolder => {
+            let path = join(&[value]);
+            // Safety check: don't copy top-level folders
+            match path.rsplit_once('\\') {
+                Some((parent, _)) if parent.contains('\\') => {
+                    out.found.insert(Dependency::Folder(parent.to_string()), DependencySource::Section);
+                }
+                _ => out.warn(format!("Skipping invalid folder of '{}' in '{}'", value, rel_path)),
+            }
+        }
+        RuleKind::Folder => {
+            for base in &rule.search {
+                let dir = resolve(base, folder, value);
+                out.looked_in(&dir);
+                out.found.extend_files(ctx.index.files_in(Path::new(&dir)), DependencySource::Section);
+            }
+        }
+        RuleKind::Texture => {
+            let path = join(&[value]);
+            let (dir, file_name) = path.rsplit_once('\\').unwrap_or(("", &path));
+            let mut textures = HashSet::new();
+            for base in &rule.search {
+                let dir = resolve(base, folder, dir);
+                out.looked_in(&dir);
+                utils::add_texture_variants(base_name(file_name), Path::new(&dir), &ctx.index, &mut textures);
+            }
+            out.found.extend_files(textures, DependencySource::Section);
+        }
+        RuleKind::File | RuleKind::Mesh => {
+            let located = rule.search.iter()
+                .map(|base| resolve(base, folder, value))
+                .inspect(|path| out.looked_in(parent(path)))
+                .find(|path| ctx.index.is_file(path))
+                .map(|path| ctx.index.real_path(&path).unwrap_or(path));
+            let path = match (located, &rule.keep_missing) {
+                (Some(path), _) => path,
+                (None, Some(base)) => {
+                    out.warn(format!("Missing '{}' in '{}'. Searched: {}", value, rel_path, rule.search.iter()
+                        .map(|base| resolve(base, folder, ""))
+                        .collect::<Vec<_>>()
+                        .join(", ")));
+                    resolve(base, folder, value)
+                }
+                (None, None) => return,
+            };
+            out.found.insert(Dependency::File(path.clone()), DependencySource::Section);
+
+            if rule.kind == RuleKind::Mesh {
+                add_mesh_textures(rule, &path, folder, ctx, out);
+            }
+            if let Some(format) = &rule.recurse {
+                if depth < MAX_RECURSION && ctx.index.is_file(&path) {
+                    apply_rules(&path, &format.to_lowercase(), ctx, depth + 1, out);
+                }
+            }
+        }
+    }
+}
+
+// Textures named inside a mesh, looked up from the folder of the parsed file
+fn add_mesh_textures(rule: &ExtractionRule, mesh_path: &str, folder: &str, ctx: &ExtractionContext, out: &mut Extraction) {
+    let mesh_lower = mesh_path.to_lowercase();
+    let mesh_textures = if mesh_lower.ends_with(".o3d") {
+        o3d::extract_o3d_textures(mesh_path, ctx)
+    } else if mesh_lower.ends_with(".x") {
+        x::extract_x_textures(mesh_path, ctx)
+    } else {
+        None
+    };
+
+    let mut textures_found = false;
+    if let Some((textures, source)) = mesh_textures {
+        // Scanned names of a disabled source still fall back to the texture folders
+        textures_found = !textures.is_empty() && ctx.uses(source);
+        let mut texture_deps = HashSet::new();
+        for tex_name in &textures {
+            utils::add_texture_variants(base_name(tex_name), Path::new(folder), &ctx.index, &mut texture_deps);
+        }
+        out.found.extend_files(texture_deps, source);
+    }
+
+    // Without texture names, .o3d meshes take the whole texture folders of the object
+    if !textures_found && mesh_lower.ends_with(".o3d") {
+        for base in &rule.fallback_folders {
+            out.found.insert(Dependency::Folder(resolve(base, folder, "")), DependencySource::Section);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::CodePages;
+    use crate::parse_cache::ParseCache;
+    use crate::phase2_extraction::extractor::ExtractorRegistry;
+    use crate::test_utils::{test_dir, write_files};
+
+    // Dependencies of `rel_path` with the default rules, and the number of diagnostics
+    fn extract(root: &Path, rel_path: &str) -> (HashSet<Dependency>, usize) {
+        let mut ctx = ExtractionContext::new(root, ParseCache::default(), CodePages::default());
+        ctx.rules = RuleSet::builtin();
+        ctx.extractors = ExtractorRegistry::builtin(&ctx.rules);
+        let extraction = ctx.extractors.for_path(rel_path).unwrap().extract(rel_path, &ctx).unwrap();
+        (extraction.found.into_dependencies(), extraction.diagnostics.len())
+    }
+
+    fn files(paths: &[&str]) -> HashSet<Dependency> {
+        paths.iter().map(|path| Dependency::File(path.to_string())).collect()
+    }
+
+    #[test]
+    fn default_rules_cover_known_formats() {
+        let rules = RuleSet::builtin();
+        let formats: HashSet<&str> = rules.formats().collect();
+        for format in ["sco", "sli", "ovh", "hum", "cfg", "sound", "zug"] {
+            assert!(formats.contains(format), "{}", format);
+        }
+        assert!(!formats.contains("bus"));
+
+        let extensions: HashSet<&str> = rules.extensions().collect();
+        assert!(extensions.contains("hum"));
+        assert!(!extensions.contains("cfg"));
+        assert!(!extensions.contains("sound"));
+    }
+
+    #[test]
+    fn reads_wav_files_of_vehicle_sound_configs() {
+        let root = test_dir("rules_ovh_sound");
+        write_files(&root, &[
+            ("Vehicles/Car/car.ovh", "[model]\nmodel.cfg\n\n[sound]\nsound.cfg\n\n[sound]\n..\\..\\Sounds\\AI\\engine.cfg\n"),
+            ("Vehicles/Car/model/model.cfg", "[texture]\nbody.bmp\n"),
+            ("Vehicles/Car/model/body.bmp", ""),
+            ("Vehicles/Car/sound/sound.cfg", "[loop]\nhorn.wav\n1\nbrake.wav\nmissing.wav\n"),
+            ("Vehicles/Car/sound/horn.wav", ""),
+            ("Vehicles/Car/sound/sound/brake.wav", ""),
+            ("Sounds/AI/engine.cfg", "[loop]\nengine.wav\n"),
+            ("Sounds/AI/engine.wav", ""),
+        ]);
+
+        // Wav files are looked up from the sound config; missing ones stay relative to the OMSI root.
+        // Model configs are not read further.
+        let (found, _) = extract(&root, "Vehicles\\Car\\car.ovh");
+        assert_eq!(found, files(&[
+            "Vehicles\\Car\\car.ovh",
+            "Vehicles\\Car\\model\\model.cfg",
+            "Vehicles\\Car\\sound\\sound.cfg",
+            "Vehicles\\Car\\sound\\horn.wav",
+            "Vehicles\\Car\\sound\\sound\\brake.wav",
+            "missing.wav",
+            "Sounds\\AI\\engine.cfg",
+            "Sounds\\AI\\engine.wav",
+        ]));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn reads_counted_vehicle_sections() {
+        let root = test_dir("rules_ovh_counted");
+        write_files(&root, &[
+            ("Vehicles/Car/car.ovh", concat!(
+                "[varnamelist]\n2\nvars.txt\n..\\..\\Scripts\\AI\\AI_varlist.txt\n",
+                "[constfile]\n1\nconst.txt\nunlisted.txt\n",
+                "[script]\n1\nmain.osc\n",
+            )),
+            ("Vehicles/Car/script/vars.txt", ""),
+            ("Vehicles/Car/script/unlisted.txt", ""),
+            ("Vehicles/Car/script/main.osc", ""),
+            ("Vehicles/Car/const.txt", ""),
+            ("Scripts/AI/AI_varlist.txt", ""),
+        ]);
+
+        let (found, _) = extract(&root, "Vehicles\\Car\\car.ovh");
+        assert_eq!(found, files(&[
+            "Vehicles\\Car\\car.ovh",
+            "Vehicles\\Car\\script\\vars.txt",
+            "Scripts\\AI\\AI_varlist.txt",
+            "Vehicles\\Car\\const.txt",
+            "Vehicles\\Car\\script\\main.osc",
+        ]));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn reads_textures_of_human_model_configs() {
+        let root = test_dir("rules_hum");
+        write_files(&root, &[
+            ("Humans/Pax/pax.hum", "[model]\nmodel\\model.cfg\n"),
+            ("Humans/Pax/model/model.cfg", "[mesh]\nbody.o3d\n[texture]\nskin.bmp\n[texture]\nhair.bmp\n[texture]\nmissing.bmp\n"),
+            ("Humans/Pax/model/skin.bmp", ""),
+            ("Humans/Pax/model/Texture/hair.bmp", ""),
+            ("Humans/Lost/lost.hum", "[model]\nmodel\\gone.cfg\n"),
+        ]);
+
+        let (found, _) = extract(&root, "Humans\\Pax\\pax.hum");
+        assert_eq!(found, files(&[
+            "Humans\\Pax\\pax.hum",
+            "Humans\\Pax\\model\\model.cfg",
+            "Humans\\Pax\\model\\skin.bmp",
+            "Humans\\Pax\\model\\Texture\\hair.bmp",
+        ]));
+
+        // A missing model config is kept so it is reported
+        let (found, _) = extract(&root, "Humans\\Lost\\lost.hum");
+        assert_eq!(found, files(&["Humans\\Lost\\lost.hum", "Humans\\Lost\\model\\gone.cfg"]));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn train_vehicles_need_a_folder_below_a_top_level_folder() {
+        let root = test_dir("rules_zug");
+        write_files(&root, &[
+            ("Trains/T/t.zug", "Vehicles\\Tram\\tram.ovh\n0\nVehicles\\Tram\\wagon.bus\n1\ntram.ovh\n0\nVehicles\\bus.bus\n0\n"),
+        ]);
+
+        let (found, diagnostics) = extract(&root, "Trains\\T\\t.zug");
+        let mut expected = files(&["Trains\\T\\t.zug"]);
+        expected.insert(Dependency::Folder("Vehicles\\Tram".to_string()));
+        assert_eq!(found, expected);
+        assert_eq!(diagnostics, 2);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn spline_textures_with_a_folder_are_found_from_the_root_and_the_spline() {
+        let root = test_dir("rules_sli");
+        write_files(&root, &[
+            ("Splines/Road/road.sli", "[texture]\nroad.bmp\n[texture]\nSplines\\Shared\\asphalt.bmp ; shared\n[texture]\nsub\\curb.bmp\n"),
+            ("Splines/Road/road.bmp", ""),
+            ("Splines/Shared/asphalt.bmp", ""),
+            ("Splines/Road/sub/curb.bmp", ""),
+        ]);
+
+        let (found, _) = extract(&root, "Splines\\Road\\road.sli");
+        assert_eq!(found, files(&[
+            "Splines\\Road\\road.sli",
+            "Splines\\Road\\road.bmp",
+            "Splines\\Shared\\asphalt.bmp",
+            "Splines\\Road\\sub\\curb.bmp",
+        ]));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn resolves_search_folders() {
+        let folder = "Sceneryobjects\\Author\\Obj";
+        assert_eq!(resolve("model", folder, "obj.o3d"), "Sceneryobjects\\Author\\Obj\\model\\obj.o3d");
+        assert_eq!(resolve("", folder, "obj.o3d"), "Sceneryobjects\\Author\\Obj\\obj.o3d");
+        assert_eq!(resolve("/", folder, "Texture\\a.jpg"), "Texture\\a.jpg");
+        assert_eq!(resolve("model", folder, "..\\..\\Shared\\s.cfg"), "Sceneryobjects\\Shared\\s.cfg");
+    }
+}
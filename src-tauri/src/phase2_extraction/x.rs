@@ -1,12 +1,15 @@
-use std::path::Path;
 use std::fs;
 use crate::phase2_extraction::utils;
+use crate::phase2_extraction::context::ExtractionContext;
+use crate::encoding;
+use crate::types::DependencySource;
 
-/// Extract texture references from .x (DirectX mesh) file
-pub fn extract_x_textures(x_path: &str, omsi_root: &Path) -> Option<Vec<String>> {
-    let full_x_path = omsi_root.join(x_path);
+/// Extract texture references from .x (DirectX mesh) file, with how they were found:
+/// text files are parsed, binary files are scanned for texture names
+pub fn extract_x_textures(x_path: &str, ctx: &ExtractionContext) -> Option<(Vec<String>, DependencySource)> {
+    let full_x_path = ctx.index.full_path(x_path);
     
-    if !full_x_path.exists() {
+    if !ctx.index.is_file(x_path) {
         return None;
     }
     
@@ -20,11 +23,13 @@ pub fn extract_x_textures(x_path: &str, omsi_root: &Path) -> Option<Vec<String>>
     let content = if let Ok(s) = String::from_utf8(buffer.clone()) {
         s
     } else {
-        // Try Windows-1252 for legacy files
-        let (cow, _, had_errors) = encoding_rs::WINDOWS_1252.decode(&buffer);
+        // Try the legacy code page for older files
+        let code_page = ctx.code_pages.for_path(x_path)
+            .unwrap_or_else(|| encoding::detect_legacy_code_page(&buffer));
+        let (cow, _, had_errors) = code_page.decode(&buffer);
         if had_errors {
             // If decoding fails, treat as binary
-            return utils::extract_textures_from_binary(&buffer);
+            return utils::extract_textures_from_binary(&buffer).map(|t| (t, DependencySource::BinaryScan));
         }
         cow.into_owned()
     };
@@ -62,6 +67,6 @@ pub fn extract_x_textures(x_path: &str, omsi_root: &Path) -> Option<Vec<String>>
     if textures.is_empty() {
         None
     } else {
-        Some(textures)
+        Some((textures, DependencySource::Section))
     }
 }
@@ -1,19 +1,21 @@
 use std::path::Path;
 use std::collections::HashSet;
 use rayon::prelude::*;
-use crate::phase2_extraction::{sco, sli, bus, ovh, hum, zug, cfg};
+use crate::phase2_extraction::context::ExtractionContext;
+use crate::types::Dependency;
 
 /// Process all dependencies starting from the initial set of files collected in Phase 1.
-/// Returns a complete set of all files that need to be included in the bundle.
-pub fn process_dependencies(initial_paths: HashSet<String>, omsi_root: &Path) -> HashSet<String> {
+/// Returns a complete set of all files and folders that need to be included in the bundle.
+/// How every dependency was found is recorded in the context; results of disabled sources are dropped.
+pub fn process_dependencies(initial_deps: HashSet<Dependency>, ctx: &ExtractionContext) -> HashSet<Dependency> {
     let mut visited = HashSet::new();
-    let mut queue: Vec<String> = Vec::new();
+    let mut queue: Vec<Dependency> = Vec::new();
 
     // Initialize queue with Phase 1 paths
-    for path in initial_paths {
-        if !visited.contains(&path) {
-            visited.insert(path.clone());
-            queue.push(path);
+    for dep in initial_deps {
+        if !visited.contains(&dep) {
+            visited.insert(dep.clone());
+            queue.push(dep);
         }
     }
 
@@ -22,49 +24,42 @@ pub fn process_dependencies(initial_paths: HashSet<String>, omsi_root: &Path) ->
         let current_batch = std::mem::take(&mut queue);
 
         // Process batch in parallel
-        let results: Vec<HashSet<String>> = current_batch.par_iter()
-            .map(|current_path| {
+        let results: Vec<HashSet<Dependency>> = current_batch.par_iter()
+            .map(|current| {
                 let mut new_items = HashSet::new();
 
-                // Check if it's a folder marker
-                if current_path.starts_with("FOLDER:") {
-                    return new_items;
-                }
+                // Folders are copied as a whole, only files are parsed
+                let current_path = match current {
+                    Dependency::File(path) => path.as_str(),
+                    _ => return new_items,
+                };
 
                 // Special handling for Money and TicketPacks folders
                 if current_path.starts_with("Money\\") || current_path.starts_with("Money/") ||
                    current_path.starts_with("TicketPacks\\") || current_path.starts_with("TicketPacks/") {
-                    if let Some(parent) = Path::new(&current_path).parent() {
+                    if let Some(parent) = Path::new(current_path).parent() {
                         let folder_str = parent.to_string_lossy().replace('/', "\\");
-                        let folder_marker = format!("FOLDER:{}", folder_str);
-                        new_items.insert(folder_marker);
+                        new_items.insert(Dependency::Folder(folder_str));
                     }
                 }
 
-                // Determine file type and extract dependencies
-                let path_lower = current_path.to_lowercase();
-                let extracted = if path_lower.ends_with(".sco") {
-                    sco::extract_sco_dependencies(&current_path, omsi_root)
-                } else if path_lower.ends_with(".sli") {
-                    sli::extract_sli_dependencies(&current_path, omsi_root)
-                } else if path_lower.ends_with(".bus") {
-                    bus::extract_bus_dependencies(&current_path, omsi_root)
-                } else if path_lower.ends_with(".ovh") {
-                    ovh::extract_ovh_dependencies(&current_path, omsi_root)
-                } else if path_lower.ends_with(".hum") {
-                    hum::extract_hum_dependencies(&current_path, omsi_root)
-                } else if path_lower.ends_with(".zug") {
-                    zug::extract_zug_dependencies(&current_path, omsi_root)
-                } else if path_lower.ends_with(".cfg") {
-                    cfg::extract_cfg_dependencies(&current_path, omsi_root)
-                } else {
-                    None
-                };
+                // Pick the extractor by file extension
+                let extracted = ctx.extractors.for_path(current_path).and_then(|extractor| {
+                    if extractor.cacheable() {
+                        ctx.cached_dependencies(current_path, || extractor.extract(current_path, ctx))
+                    } else {
+                        extractor.extract(current_path, ctx)
+                    }
+                });
 
-                if let Some(deps) = extracted {
-                    for dep in deps {
-                        new_items.insert(dep);
+                if let Some(mut extraction) = extracted {
+                    for message in &extraction.diagnostics {
+                        log_warn!("{}", message);
                     }
+                    // Extractors list the parsed file itself, which says nothing about how it was found
+                    extraction.found.remove(current);
+                    ctx.provenance.record(&extraction.found);
+                    new_items.extend(extraction.found.into_enabled(&ctx.disabled_sources));
                 }
                 
                 new_items
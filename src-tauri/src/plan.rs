@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::bundling::{prepare_bundle, resolve_dependencies, PreparedBundle};
+use crate::file_index::{fnv1a, normalize_key, FNV_OFFSET};
+use crate::types::{BundlePlan, BundleRequest, Dependency, PlanNode, PlanOverrides, PlanOverridesResult, PlanState};
+use crate::utils::app_data_dir;
+
+/// Folder below the app data directory holding the saved overrides, one file per map
+const PLANS_DIR: &str = "plans";
+
+/// Overrides file content; the map folder is kept for reference only
+#[derive(Serialize, Deserialize)]
+struct SavedOverrides {
+    map_folder: String,
+    #[serde(flatten)]
+    overrides: PlanOverrides,
+}
+
+impl PlanOverrides {
+    /// Length of the most specific rule matching `key` (the path itself or a parent folder)
+    fn best_match(rules: &[String], key: &str) -> Option<usize> {
+        rules.iter()
+            .map(|rule| normalize_key(rule))
+            .filter(|rule| key == rule || key.starts_with(&format!("{}\\", rule)))
+            .map(|rule| rule.len())
+            .max()
+    }
+
+    /// Whether a file or folder (relative to the OMSI root) is excluded.
+    /// The most specific rule wins, so a forced file inside an excluded folder is still bundled.
+    pub fn is_excluded(&self, rel: &str) -> bool {
+        if self.excluded.is_empty() {
+            return false;
+        }
+        let key = normalize_key(rel);
+        match (Self::best_match(&self.excluded, &key), Self::best_match(&self.included, &key)) {
+            (Some(excluded), Some(included)) => excluded > included,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    fn state(&self, rel: &str) -> PlanState {
+        if self.is_excluded(rel) {
+            PlanState::Excluded
+        } else if Self::best_match(&self.included, &normalize_key(rel)).is_some() {
+            PlanState::Forced
+        } else {
+            PlanState::Included
+        }
+    }
+}
+
+fn overrides_path(map_folder: &Path) -> PathBuf {
+    let key = normalize_key(&map_folder.to_string_lossy());
+    app_data_dir()
+        .join(PLANS_DIR)
+        .join(format!("{:016x}.json", fnv1a(FNV_OFFSET, key.as_bytes())))
+}
+
+/// Overrides saved for a map folder (empty when none were saved)
+pub fn load_overrides(map_folder: &Path) -> PlanOverrides {
+    fs::read(overrides_path(map_folder))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<SavedOverrides>(&bytes).ok())
+        .map(|saved| saved.overrides)
+        .unwrap_or_default()
+}
+
+/// Save the overrides of a map folder, used by every later bundle of the map
+pub fn save_overrides(map_folder: String, overrides: PlanOverrides) -> PlanOverridesResult {
+    let clean = |rules: Vec<String>| {
+        let mut rules: Vec<String> = rules.iter()
+            .map(|r| r.trim().replace('/', "\\").trim_matches('\\').to_string())
+            .filter(|r| !r.is_empty())
+            .collect();
+        rules.sort_by_key(|r| normalize_key(r));
+        rules.dedup_by_key(|r| normalize_key(r));
+        rules
+    };
+    let overrides = PlanOverrides {
+        excluded: clean(overrides.excluded),
+        included: clean(overrides.included),
+    };
+    let path = overrides_path(Path::new(&map_folder));
+
+    let saved = if overrides.excluded.is_empty() && overrides.included.is_empty() {
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    } else {
+        let saved = SavedOverrides { map_folder, overrides };
+        path.parent()
+            .map(fs::create_dir_all)
+            .transpose()
+            .map_err(|e| e.to_string())
+            .and_then(|_| serde_json::to_vec_pretty(&saved).map_err(|e| e.to_string()))
+            .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()))
+    };
+
+    match saved {
+        Ok(()) => PlanOverridesResult { success: true, error: None },
+        Err(e) => PlanOverridesResult { success: false, error: Some(format!("Failed to save overrides: {}", e)) },
+    }
+}
+
+/// Folder of the plan tree while it is built, keyed by lowercased name
+#[derive(Default)]
+struct TreeDir {
+    dirs: BTreeMap<String, (String, TreeDir)>,
+    files: BTreeMap<String, String>,
+}
+
+impl TreeDir {
+    fn insert(&mut self, path: &str) {
+        let mut dir = self;
+        let mut parts = path.split('\\').filter(|p| !p.is_empty()).peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                dir.files.entry(part.to_lowercase()).or_insert_with(|| part.to_string());
+            } else {
+                dir = &mut dir.dirs.entry(part.to_lowercase()).or_insert_with(|| (part.to_string(), TreeDir::default())).1;
+            }
+        }
+    }
+
+    fn into_nodes(self, prefix: &str, plan: &PreparedBundle) -> Vec<PlanNode> {
+        let join = |name: &str| if prefix.is_empty() { name.to_string() } else { format!("{}\\{}", prefix, name) };
+        let mut nodes: Vec<PlanNode> = self.dirs.into_values()
+            .map(|(name, dir)| {
+                let path = join(&name);
+                let children = dir.into_nodes(&path, plan);
+                PlanNode {
+                    size: children.iter().map(|c| c.size).sum(),
+                    missing: !children.is_empty() && children.iter().all(|c| c.missing),
+                    source: None,
+                    confidence: None,
+                    state: plan.overrides.state(&path),
+                    name,
+                    path,
+                    is_dir: true,
+                    children,
+                }
+            })
+            .collect();
+        nodes.extend(self.files.into_values().map(|name| {
+            let path = join(&name);
+            let exists = plan.ctx.index.is_file(&path);
+            let source = plan.ctx.provenance.source(&path);
+            PlanNode {
+                size: if exists { fs::metadata(plan.ctx.index.full_path(&path)).map(|m| m.len()).unwrap_or(0) } else { 0 },
+                missing: !exists,
+                source: Some(source),
+                confidence: Some(source.confidence()),
+                state: plan.overrides.state(&path),
+                name,
+                path,
+                is_dir: false,
+                children: Vec::new(),
+            }
+        }));
+        nodes
+    }
+}
+
+fn count(nodes: &[PlanNode], filter: &impl Fn(&PlanNode) -> bool) -> usize {
+    nodes.iter()
+        .map(|n| if n.is_dir { count(&n.children, filter) } else { filter(n) as usize })
+        .sum()
+}
+
+/// Resolve a bundle request without building it and return the dependencies as a folder tree.
+/// The map folders themselves are always bundled whole and are not part of the tree.
+pub fn get_bundle_plan(request: BundleRequest) -> BundlePlan {
+    let failure = |error: String| BundlePlan {
+        omsi_root: String::new(),
+        overrides: PlanOverrides::default(),
+        nodes: Vec::new(),
+        files: 0,
+        excluded: 0,
+        missing: 0,
+        error: Some(error),
+    };
+    let mut plan = match prepare_bundle(&request, &None) {
+        Ok(plan) => plan,
+        Err(e) => return failure(e),
+    };
+
+    let initial_deps = std::mem::take(&mut plan.initial_deps);
+    let (deps, excluded) = resolve_dependencies(initial_deps, &plan.overrides, &plan.omsi_root, &plan.ctx);
+    if let Err(e) = plan.ctx.cache.save() {
+        log_warn!("Failed to save parse cache: {}", e);
+    }
+
+    let map_prefixes: Vec<String> = plan.maps.iter()
+        .map(|(_, _, rel)| format!("{}\\", normalize_key(rel)))
+        .collect();
+    let mut tree = TreeDir::default();
+    for dep in deps.iter().flatten().chain(excluded.iter()) {
+        let files = match dep {
+            Dependency::Folder(folder) => plan.ctx.index.files_under(Path::new(folder)),
+            other => vec![other.path().to_string()],
+        };
+        for file in files {
+            let key = normalize_key(&file);
+            if !map_prefixes.iter().any(|prefix| key.starts_with(prefix.as_str())) {
+                tree.insert(&plan.ctx.index.real_path(&file).unwrap_or(file));
+            }
+        }
+    }
+
+    let nodes = tree.into_nodes("", &plan);
+    BundlePlan {
+        omsi_root: plan.omsi_root.to_string_lossy().to_string(),
+        files: count(&nodes, &|n| n.state != PlanState::Excluded && !n.missing),
+        excluded: count(&nodes, &|n| n.state == PlanState::Excluded),
+        missing: count(&nodes, &|n| n.state != PlanState::Excluded && n.missing),
+        overrides: plan.overrides,
+        nodes,
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(excluded: &[&str], included: &[&str]) -> PlanOverrides {
+        PlanOverrides {
+            excluded: excluded.iter().map(|r| r.to_string()).collect(),
+            included: included.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn more_specific_rules_win() {
+        let rules = overrides(&["Humans", "Sceneryobjects\\Trees\\Summer"], &["Sceneryobjects\\Trees"]);
+        assert!(rules.is_excluded("humans\\driver.hum"));
+        assert!(rules.is_excluded("Sceneryobjects\\trees\\SUMMER\\oak.sco"));
+        assert!(!rules.is_excluded("Sceneryobjects\\Trees\\Winter\\oak.sco"));
+        // Only whole path components match
+        assert!(!rules.is_excluded("Humans2\\driver.hum"));
+        assert!(!rules.is_excluded("Texture\\road.dds"));
+    }
+
+    #[test]
+    fn forced_files_inside_excluded_folders_stay_included() {
+        let rules = overrides(&["Sceneryobjects\\Trees"], &["Sceneryobjects\\Trees\\oak.sco", "Texture"]);
+        assert!(rules.is_excluded("Sceneryobjects\\Trees\\birch.sco"));
+        assert!(!rules.is_excluded("sceneryobjects/trees/OAK.sco"));
+        assert_eq!(rules.state("Sceneryobjects\\Trees\\oak.sco"), PlanState::Forced);
+        assert_eq!(rules.state("Sceneryobjects\\Trees\\birch.sco"), PlanState::Excluded);
+        assert_eq!(rules.state("Texture\\road.dds"), PlanState::Forced);
+        assert_eq!(rules.state("Splines\\road.sli"), PlanState::Included);
+
+        // The same path in both lists is bundled
+        let rules = overrides(&["Texture\\road.dds"], &["texture\\ROAD.dds"]);
+        assert!(!rules.is_excluded("Texture\\road.dds"));
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use crate::file_index::normalize_key;
+use crate::types::{Confidence, Dependency, DependencySource, DependencyTag};
+
+impl DependencySource {
+    /// Parse a source name as sent by the frontend ("section", "path_scan", "binary_scan", "prefix_match")
+    pub fn parse(name: &str) -> Result<DependencySource, String> {
+        match name.to_lowercase().replace('-', "_").as_str() {
+            "section" => Ok(DependencySource::Section),
+            "path_scan" => Ok(DependencySource::PathScan),
+            "binary_scan" => Ok(DependencySource::BinaryScan),
+            "prefix_match" => Ok(DependencySource::PrefixMatch),
+            other => Err(format!("Unknown dependency source: {}", other)),
+        }
+    }
+
+    pub fn confidence(self) -> Confidence {
+        match self {
+            DependencySource::Section => Confidence::Exact,
+            _ => Confidence::Heuristic,
+        }
+    }
+}
+
+/// Dependencies found in a file, each with the most exact source that found it
+#[derive(Debug, Default, Clone)]
+pub struct FoundPaths(HashMap<Dependency, DependencySource>);
+
+impl FoundPaths {
+    pub fn insert(&mut self, dependency: Dependency, source: DependencySource) {
+        self.0.entry(dependency)
+            .and_modify(|s| *s = (*s).min(source))
+            .or_insert(source);
+    }
+
+    pub fn extend(&mut self, dependencies: impl IntoIterator<Item = Dependency>, source: DependencySource) {
+        for dependency in dependencies {
+            self.insert(dependency, source);
+        }
+    }
+
+    /// Add files by path
+    pub fn extend_files(&mut self, paths: impl IntoIterator<Item = String>, source: DependencySource) {
+        self.extend(paths.into_iter().map(Dependency::File), source);
+    }
+
+    pub fn merge(&mut self, other: FoundPaths) {
+        for (path, source) in other.0 {
+            self.insert(path, source);
+        }
+    }
+
+    pub fn remove(&mut self, dependency: &Dependency) {
+        self.0.remove(dependency);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Dependency, DependencySource)> {
+        self.0.iter().map(|(dependency, source)| (dependency, *source))
+    }
+
+    /// All dependencies, whatever their source
+    pub fn into_dependencies(self) -> HashSet<Dependency> {
+        self.0.into_keys().collect()
+    }
+
+    /// Dependencies found by a source that is not disabled
+    pub fn into_enabled(self, disabled: &[DependencySource]) -> HashSet<Dependency> {
+        self.0.into_iter()
+            .filter(|(_, source)| !disabled.contains(source))
+            .map(|(dependency, _)| dependency)
+            .collect()
+    }
+
+    /// All dependencies plus the sources of the heuristic ones by path, as stored in the parse cache
+    pub fn to_tagged(&self) -> (Vec<Dependency>, HashMap<String, DependencySource>) {
+        let tags = self.0.iter()
+            .filter(|(_, source)| **source != DependencySource::Section)
+            .map(|(dependency, source)| (dependency.path().to_string(), *source))
+            .collect();
+        (self.0.keys().cloned().collect(), tags)
+    }
+
+    /// Inverse of `to_tagged`; dependencies without a tag were found exactly
+    pub fn from_tagged(dependencies: Vec<Dependency>, tags: HashMap<String, DependencySource>) -> FoundPaths {
+        let mut found = FoundPaths::default();
+        for dependency in dependencies {
+            let source = tags.get(dependency.path()).copied().unwrap_or(DependencySource::Section);
+            found.insert(dependency, source);
+        }
+        found
+    }
+}
+
+/// Sources of every path found during a run. A path found several ways keeps the most exact source.
+#[derive(Default)]
+pub struct Provenance {
+    sources: Mutex<HashMap<String, (String, DependencySource)>>,
+}
+
+impl Provenance {
+    pub fn record(&self, found: &FoundPaths) {
+        let mut sources = self.sources.lock().unwrap();
+        for (dependency, source) in found.iter() {
+            let path = dependency.path();
+            sources.entry(normalize_key(path))
+                .and_modify(|(_, s)| *s = (*s).min(source))
+                .or_insert_with(|| (path.to_string(), source));
+        }
+    }
+
+    /// Source of a path; paths that were not recorded (e.g. forced by the user) count as exact
+    pub fn source(&self, path: &str) -> DependencySource {
+        self.sources.lock().unwrap()
+            .get(&normalize_key(path))
+            .map(|(_, source)| *source)
+            .unwrap_or(DependencySource::Section)
+    }
+
+    /// Paths only found by heuristic sources, sorted by path
+    pub fn heuristic_tags(&self, is_bundled: impl Fn(&str) -> bool) -> Vec<DependencyTag> {
+        let mut tags: Vec<DependencyTag> = self.sources.lock().unwrap()
+            .values()
+            .filter(|(_, source)| source.confidence() == Confidence::Heuristic)
+            .map(|(path, source)| DependencyTag {
+                path: path.clone(),
+                source: *source,
+                confidence: source.confidence(),
+                bundled: is_bundled(path),
+            })
+            .collect();
+        tags.sort_by(|a, b| a.path.cmp(&b.path));
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_most_exact_source() {
+        let file = |path: &str| Dependency::File(path.to_string());
+        let mut found = FoundPaths::default();
+        found.insert(file("Texture\\a.jpg"), DependencySource::PrefixMatch);
+        found.insert(file("Texture\\a.jpg"), DependencySource::Section);
+        found.insert(file("Texture\\b.jpg"), DependencySource::BinaryScan);
+        found.insert(file("Texture\\c.jpg"), DependencySource::PathScan);
+
+        let (paths, tags) = found.to_tagged();
+        assert_eq!(paths.len(), 3);
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags.get("Texture\\a.jpg"), None);
+
+        let enabled = FoundPaths::from_tagged(paths, tags).into_enabled(&[DependencySource::BinaryScan]);
+        assert!(enabled.contains(&file("Texture\\a.jpg")));
+        assert!(!enabled.contains(&file("Texture\\b.jpg")));
+        assert!(enabled.contains(&file("Texture\\c.jpg")));
+    }
+}
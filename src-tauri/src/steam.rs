@@ -0,0 +1,347 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::bundle_ignore::same_path;
+use crate::omsi_root::{is_omsi_root, list_map_folders};
+use crate::types::OmsiInstallation;
+
+/// Steam app id of OMSI 2
+pub const OMSI_APP_ID: &str = "252530";
+
+/// A node of Valve's KeyValues text format (`libraryfolders.vdf`, `appmanifest_*.acf`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Vdf {
+    Value(String),
+    Object(Vec<(String, Vdf)>),
+}
+
+impl Vdf {
+    /// Child node by key (keys are case-insensitive in KeyValues)
+    pub fn get(&self, key: &str) -> Option<&Vdf> {
+        match self {
+            Vdf::Object(children) => children.iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            Vdf::Value(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Vdf::Value(value) => Some(value),
+            Vdf::Object(_) => None,
+        }
+    }
+
+    pub fn children(&self) -> &[(String, Vdf)] {
+        match self {
+            Vdf::Object(children) => children,
+            Vdf::Value(_) => &[],
+        }
+    }
+}
+
+/// Split KeyValues text into quoted strings, bare words and braces, dropping `//` comments
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' => tokens.push(c.to_string()),
+            '"' => {
+                let mut token = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some('n') => token.push('\n'),
+                            Some('t') => token.push('\t'),
+                            Some(other) => token.push(other),
+                            None => break,
+                        },
+                        _ => token.push(c),
+                    }
+                }
+                // Quoted strings are kept distinguishable from braces
+                tokens.push(format!("\"{}", token));
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut token = format!("\"{}", c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+        }
+    }
+    tokens
+}
+
+fn parse_object(tokens: &mut std::iter::Peekable<std::vec::IntoIter<String>>) -> Result<Vec<(String, Vdf)>, String> {
+    let mut children = Vec::new();
+    while let Some(token) = tokens.next() {
+        if token == "}" {
+            return Ok(children);
+        }
+        let key = match token.strip_prefix('"') {
+            Some(key) => key.to_string(),
+            None => return Err(format!("Unexpected '{}'", token)),
+        };
+        let value = match tokens.next() {
+            Some(t) if t == "{" => Vdf::Object(parse_object(tokens)?),
+            Some(t) if t.starts_with('"') => Vdf::Value(t[1..].to_string()),
+            Some(t) => return Err(format!("Unexpected '{}' after \"{}\"", t, key)),
+            None => return Err(format!("Missing value for \"{}\"", key)),
+        };
+        children.push((key, value));
+    }
+    Ok(children)
+}
+
+/// Parse a KeyValues document into its root object
+pub fn parse_vdf(text: &str) -> Result<Vdf, String> {
+    let mut tokens = tokenize(text).into_iter().peekable();
+    let root = parse_object(&mut tokens)?;
+    if tokens.peek().is_some() {
+        return Err("Unbalanced braces".to_string());
+    }
+    Ok(Vdf::Object(root))
+}
+
+/// Library folders listed in `libraryfolders.vdf`. Handles the current format
+/// (`"0" { "path" "..." }`) and the old one (`"1" "D:\\SteamLibrary"`).
+pub fn parse_library_folders(text: &str) -> Result<Vec<PathBuf>, String> {
+    let root = parse_vdf(text)?;
+    let folders = root.get("libraryfolders")
+        .or_else(|| root.get("LibraryFolders"))
+        .ok_or("No libraryfolders section")?;
+
+    Ok(folders.children().iter()
+        .filter(|(key, _)| key.chars().all(|c| c.is_ascii_digit()))
+        .filter_map(|(_, value)| match value {
+            Vdf::Value(path) => Some(path.as_str()),
+            Vdf::Object(_) => value.get("path").and_then(Vdf::as_str),
+        })
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Install folder name (below `steamapps\common`) from an `appmanifest_*.acf`
+pub fn parse_app_install_dir(text: &str) -> Result<String, String> {
+    parse_vdf(text)?
+        .get("AppState")
+        .and_then(|state| state.get("installdir"))
+        .and_then(Vdf::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "No installdir in app manifest".to_string())
+}
+
+/// Resolve a Windows library path found in a Steam installed under Wine/Proton
+/// through the prefix's `dosdevices` links (`D:\Games` -> `<prefix>/dosdevices/d:/Games`)
+fn wine_path(prefix: Option<&Path>, path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    let bytes = text.as_bytes();
+    match prefix {
+        Some(prefix) if !cfg!(windows) && bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' => {
+            let drive = format!("{}:", text[..1].to_lowercase());
+            let rest = text[2..].trim_start_matches(['\\', '/']).replace('\\', "/");
+            prefix.join("dosdevices").join(drive).join(rest)
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// OMSI 2 roots found in the libraries of one Steam installation.
+/// `wine_prefix` is set for a Windows Steam running under Wine/Proton.
+pub fn find_in_steam(steam_root: &Path, wine_prefix: Option<&Path>) -> Vec<(PathBuf, PathBuf)> {
+    let mut libraries = vec![steam_root.to_path_buf()];
+    let vdf = ["steamapps/libraryfolders.vdf", "config/libraryfolders.vdf"].iter()
+        .map(|p| steam_root.join(p))
+        .find(|p| p.is_file());
+    if let Some(vdf) = vdf {
+        match fs::read_to_string(&vdf).map_err(|e| e.to_string()).and_then(|text| parse_library_folders(&text)) {
+            Ok(folders) => libraries.extend(folders.iter().map(|f| wine_path(wine_prefix, f))),
+            Err(e) => log_warn!("[STEAM] Could not read {}: {}", vdf.display(), e),
+        }
+    }
+
+    let mut roots: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for library in libraries {
+        let steamapps = library.join("steamapps");
+        let manifest = steamapps.join(format!("appmanifest_{}.acf", OMSI_APP_ID));
+        let install_dir = match fs::read_to_string(&manifest) {
+            Ok(text) => match parse_app_install_dir(&text) {
+                Ok(dir) => dir,
+                Err(e) => {
+                    log_warn!("[STEAM] {}: {}", manifest.display(), e);
+                    continue;
+                }
+            },
+            Err(_) => continue,
+        };
+        let root = steamapps.join("common").join(install_dir);
+        if !roots.iter().any(|(r, _)| same_path(r, &root)) {
+            roots.push((root, library));
+        }
+    }
+    roots
+}
+
+#[cfg(windows)]
+fn registry_paths() -> (Vec<PathBuf>, Vec<PathBuf>) {
+    use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+    use winreg::RegKey;
+
+    let read = |hive, key: &str, value: &str| -> Option<PathBuf> {
+        RegKey::predef(hive).open_subkey(key).ok()?
+            .get_value::<String, _>(value).ok()
+            .map(PathBuf::from)
+    };
+    let steam = [
+        read(HKEY_CURRENT_USER, "Software\\Valve\\Steam", "SteamPath"),
+        read(HKEY_LOCAL_MACHINE, "SOFTWARE\\WOW6432Node\\Valve\\Steam", "InstallPath"),
+        read(HKEY_LOCAL_MACHINE, "SOFTWARE\\Valve\\Steam", "InstallPath"),
+    ];
+    // Steam registers every installed game for uninstallation
+    let uninstall = format!("Steam App {}", OMSI_APP_ID);
+    let omsi = [
+        read(HKEY_LOCAL_MACHINE, &format!("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\{}", uninstall), "InstallLocation"),
+        read(HKEY_LOCAL_MACHINE, &format!("SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\{}", uninstall), "InstallLocation"),
+    ];
+    (steam.into_iter().flatten().collect(), omsi.into_iter().flatten().collect())
+}
+
+/// Steam installations to look at, with the Wine prefix for Windows Steam under Wine/Proton
+fn steam_roots() -> (Vec<(PathBuf, Option<PathBuf>)>, Vec<PathBuf>) {
+    #[cfg(windows)]
+    {
+        let (mut steam, omsi) = registry_paths();
+        steam.push(PathBuf::from("C:\\Program Files (x86)\\Steam"));
+        (steam.into_iter().map(|p| (p, None)).collect(), omsi)
+    }
+    #[cfg(not(windows))]
+    {
+        let home = match std::env::var_os("HOME") {
+            Some(home) => PathBuf::from(home),
+            None => return (Vec::new(), Vec::new()),
+        };
+        let mut steam: Vec<(PathBuf, Option<PathBuf>)> = [
+            ".steam/steam",
+            ".steam/root",
+            ".local/share/Steam",
+            ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+        ].iter().map(|p| (home.join(p), None)).collect();
+
+        // Windows Steam installed into a Wine prefix
+        let wine_prefix = std::env::var_os("WINEPREFIX").map(PathBuf::from).unwrap_or_else(|| home.join(".wine"));
+        steam.push((wine_prefix.join("drive_c/Program Files (x86)/Steam"), Some(wine_prefix)));
+        (steam, Vec::new())
+    }
+}
+
+fn installation(root: &Path, library: Option<&Path>) -> OmsiInstallation {
+    let maps_dir = root.join("maps");
+    OmsiInstallation {
+        root: root.to_string_lossy().to_string(),
+        library: library.map(|l| l.to_string_lossy().to_string()),
+        maps: list_map_folders(root).iter()
+            .map(|map| map.strip_prefix(&maps_dir).unwrap_or(map).to_string_lossy().replace('/', "\\"))
+            .collect(),
+    }
+}
+
+/// Find OMSI 2 installations through the Steam libraries of this machine
+pub fn discover_omsi_installations() -> Vec<OmsiInstallation> {
+    let (steam, direct) = steam_roots();
+    let mut found: Vec<(PathBuf, Option<PathBuf>)> = direct.into_iter().map(|root| (root, None)).collect();
+    for (steam_root, wine_prefix) in steam.iter().filter(|(p, _)| p.is_dir()) {
+        found.extend(find_in_steam(steam_root, wine_prefix.as_deref())
+            .into_iter()
+            .map(|(root, library)| (root, Some(library))));
+    }
+
+    // ~/.steam/steam and ~/.steam/root usually link to the same installation
+    let mut installations: Vec<OmsiInstallation> = Vec::new();
+    let mut seen: Vec<PathBuf> = Vec::new();
+    for (root, library) in found {
+        let canonical = fs::canonicalize(&root).unwrap_or_else(|_| root.clone());
+        if !is_omsi_root(&root) || seen.iter().any(|s| same_path(s, &canonical)) {
+            continue;
+        }
+        log_info!("[STEAM] Found OMSI 2 in {}", root.display());
+        installations.push(installation(&root, library.as_deref()));
+        seen.push(canonical);
+    }
+    installations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_dir;
+
+    const LIBRARY_FOLDERS: &str = include_str!("../tests/fixtures/steam/libraryfolders.vdf");
+    const LIBRARY_FOLDERS_OLD: &str = include_str!("../tests/fixtures/steam/libraryfolders_old.vdf");
+    const APP_MANIFEST: &str = include_str!("../tests/fixtures/steam/appmanifest_252530.acf");
+
+    #[test]
+    fn parses_library_folders() {
+        let folders = parse_library_folders(LIBRARY_FOLDERS).unwrap();
+        assert_eq!(folders, vec![
+            PathBuf::from("C:\\Program Files (x86)\\Steam"),
+            PathBuf::from("D:\\SteamLibrary"),
+        ]);
+    }
+
+    #[test]
+    fn parses_old_library_folders() {
+        let folders = parse_library_folders(LIBRARY_FOLDERS_OLD).unwrap();
+        assert_eq!(folders, vec![PathBuf::from("E:\\Games\\Steam Library")]);
+    }
+
+    #[test]
+    fn parses_app_manifest() {
+        assert_eq!(parse_app_install_dir(APP_MANIFEST).unwrap(), "OMSI 2");
+        assert!(parse_app_install_dir("\"AppState\" { \"appid\" \"252530\" }").is_err());
+        assert!(parse_vdf("\"AppState\" { \"installdir\" ").is_err());
+    }
+
+    #[test]
+    fn finds_omsi_in_second_library() {
+        let base = test_dir("steam_libraries");
+        let steam = base.join("Steam");
+        let library = base.join("Library");
+        fs::create_dir_all(steam.join("steamapps")).unwrap();
+        fs::create_dir_all(library.join("steamapps/common/OMSI 2/maps/Author/Grundorf")).unwrap();
+        let vdf = format!(
+            "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n\t\"1\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+            steam.to_string_lossy().replace('\\', "\\\\"),
+            library.to_string_lossy().replace('\\', "\\\\"),
+        );
+        fs::write(steam.join("steamapps/libraryfolders.vdf"), vdf).unwrap();
+        fs::write(library.join("steamapps/appmanifest_252530.acf"), APP_MANIFEST).unwrap();
+        let root = library.join("steamapps/common/OMSI 2");
+        fs::write(root.join("Omsi.exe"), b"").unwrap();
+        fs::write(root.join("maps/Author/Grundorf/global.cfg"), b"").unwrap();
+
+        let found = find_in_steam(&steam, None);
+        assert_eq!(found.len(), 1);
+        assert!(same_path(&found[0].0, &root));
+        assert!(is_omsi_root(&found[0].0));
+        let install = installation(&found[0].0, Some(&found[0].1));
+        assert_eq!(install.maps, vec!["Author\\Grundorf".to_string()]);
+
+        let _ = fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,20 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Empty temporary folder for a test. `name` must be unique across all tests,
+/// the process id keeps parallel test runs apart.
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("omsi_test_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write `files` (path relative to `root`, content), creating their folders
+pub fn write_files(root: &Path, files: &[(&str, &str)]) {
+    for (path, content) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,18 +23,362 @@ pub struct DependencyResult {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BundleRequest {
+    /// Map to bundle; empty for an asset pack
+    #[serde(default)]
     pub map_folder: String,
+    /// Further maps bundled together with `map_folder` (same OMSI folder), sharing their assets
+    pub map_folders: Option<Vec<String>>,
+    /// Files or folders (absolute or relative to `omsi_root`) bundled as an asset pack, without a map
+    pub assets: Option<Vec<String>>,
+    /// OMSI 2 root folder. Required for asset packs; for maps it is detected above the map folder when omitted.
+    pub omsi_root: Option<String>,
+    /// Further asset roots (e.g. a dev install or a shared asset library) searched in order
+    /// for dependencies missing in the OMSI root
+    pub asset_roots: Option<Vec<String>>,
     pub addon_folder: Option<String>,
+    /// Further addon folders, anywhere on disk
+    pub addon_folders: Option<Vec<AddonFolder>>,
+    /// Folder the bundle is written to; the map folder when omitted. Required for asset packs.
     pub output_folder: Option<String>,
     pub zip_name: Option<String>,
     pub readme_path: Option<String>,
     pub compression_method: Option<String>,
     pub compression_level: Option<u8>,
+    /// Diagnostics verbosity for this run ("error", "warn", "info", "debug")
+    pub log_level: Option<String>,
+    /// Attach the phase 1 scan report to the result
+    pub include_scan_report: Option<bool>,
+    /// Also write the scan report as text to this file
+    pub scan_report_path: Option<String>,
+    /// Code page for legacy text files ("auto", "1250", "1252", ...). Defaults to auto-detection.
+    pub code_page: Option<String>,
+    /// Per-folder code page overrides, keyed by folder path (absolute or relative to the OMSI root)
+    pub folder_code_pages: Option<HashMap<String, String>>,
+    /// Handling of non-ASCII file names in the archive ("utf8", "transliterate", "reject"). Defaults to "utf8".
+    pub filename_policy: Option<String>,
+    /// Split the bundle into self-contained archives of at most this many MB each, measured as written
+    /// (compressed data, headers and the volume manifest included)
+    pub max_volume_size_mb: Option<u64>,
+    /// Archive format ("zip", "7z", "tar.zst"). Defaults to "zip".
+    pub archive_format: Option<String>,
+    /// Byte-identical output for identical inputs: sorted entries, fixed timestamps and permissions
+    pub reproducible: Option<bool>,
+    /// Timestamp (Unix seconds) for reproducible mode. Defaults to `SOURCE_DATE_EPOCH`, then 1980-01-01.
+    pub source_date_epoch: Option<i64>,
+    /// Heuristic dependency sources to ignore ("path_scan", "binary_scan", "prefix_match")
+    pub disabled_sources: Option<Vec<String>>,
+}
+
+/// A folder copied into the bundle as a whole
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddonFolder {
+    pub path: String,
+    /// Path inside the archive (e.g. `Sceneryobjects\MyObjects`). Required for folders outside the OMSI root.
+    pub mount: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BundleResult {
     pub success: bool,
     pub output_path: Option<String>,
+    /// All written archives; more than one when the bundle was split into volumes
+    pub output_paths: Vec<String>,
+    pub error: Option<String>,
+    pub scan_report: Option<ScanReport>,
+    /// Archive paths that contain non-ASCII characters (before any transliteration)
+    pub non_ascii_names: Vec<String>,
+    /// Dependencies found only by heuristic sources
+    pub heuristic_dependencies: Vec<DependencyTag>,
+    /// Every dependency outside the bundled map folders, with what became of it
+    pub dependencies: Vec<DependencyEntry>,
+}
+
+/// A dependency relative to the OMSI root
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "path", rename_all = "snake_case")]
+pub enum Dependency {
+    /// A single file
+    File(String),
+    /// A folder bundled with everything in it
+    Folder(String),
+    /// A file only found by heuristics that does not exist; skipped without a warning
+    Optional(String),
+    /// A referenced file or folder that exists in none of the asset roots
+    Missing(String),
+    /// A file or folder left out by the plan overrides
+    Excluded(String),
+}
+
+/// Kind of asset a dependency is, by its top-level folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyCategory {
+    Sceneryobjects,
+    Splines,
+    Textures,
+    Vehicles,
+    Humans,
+    Trains,
+    Sounds,
+    Scripts,
+    MoneySystems,
+    TicketPacks,
+    Maps,
+    Other,
+}
+
+/// A dependency with its category and the way it was found
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyEntry {
+    #[serde(flatten)]
+    pub dependency: Dependency,
+    pub category: DependencyCategory,
+    pub source: DependencySource,
+}
+
+/// How a dependency was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencySource {
+    /// Path line of a known section or list (`[object]`, `[spline]`, `[mesh]`, ...)
+    Section,
+    /// Path-like text scanned from map and config files
+    PathScan,
+    /// Texture names scanned from the bytes of a mesh file
+    BinaryScan,
+    /// Textures whose name starts with the name of their object
+    PrefixMatch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    Exact,
+    Heuristic,
+}
+
+/// A dependency with the way it was found
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyTag {
+    /// Path relative to the OMSI root
+    pub path: String,
+    pub source: DependencySource,
+    pub confidence: Confidence,
+    /// False when the source was disabled or the path was excluded
+    pub bundled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyRequest {
+    /// Bundle to check; for a split bundle any of its volumes (all volumes are checked)
+    pub archive_path: String,
+    /// Files or folders relative to the OMSI root the bundle may reference without containing them
+    pub requirements: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyResult {
+    /// True when every entry is intact, matches its manifest and every reference resolves
+    pub success: bool,
+    /// Number of files in the bundle
+    pub files: usize,
+    /// Entries that failed the CRC check or could not be extracted
+    pub corrupt_entries: Vec<String>,
+    /// Files listed in a volume manifest but missing from the volume
+    pub missing_entries: Vec<String>,
+    /// Files in a volume that its manifest does not list
+    pub unlisted_entries: Vec<String>,
+    /// Volumes of a split bundle that could not be found next to the given one
+    pub missing_volumes: Vec<String>,
+    /// Map folders found in the bundle
+    pub maps: Vec<String>,
+    /// References that resolve neither inside the bundle nor to a declared requirement (folders end in `\`)
+    pub unresolved: Vec<String>,
     pub error: Option<String>,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstallRequest {
+    /// Bundle to install; for a split bundle any of its volumes
+    pub archive_path: String,
+    /// OMSI 2 folder to install into
+    pub omsi_root: String,
+    /// Decision per conflicting file ("keep" or "overwrite"), keyed by the path from the plan
+    pub resolutions: Option<HashMap<String, String>>,
+    /// Decision for conflicts without an entry in `resolutions`. Without it, unresolved conflicts abort the install.
+    pub default_resolution: Option<String>,
+}
+
+/// A bundled file that exists in the OMSI folder with different content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallConflict {
+    /// Path relative to the OMSI root, as stored in the bundle
+    pub path: String,
+    pub existing_size: u64,
+    pub bundle_size: u64,
+    pub existing_hash: String,
+    pub bundle_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstallPlan {
+    /// Files that don't exist yet
+    pub new_files: Vec<String>,
+    /// Files that already exist with the same content
+    pub identical: Vec<String>,
+    pub conflicts: Vec<InstallConflict>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstallResult {
+    pub success: bool,
+    /// Files written (new and overwritten)
+    pub installed: Vec<String>,
+    /// Conflicting files left untouched
+    pub kept: Vec<String>,
+    /// Existing files replaced after a backup
+    pub overwritten: Vec<String>,
+    /// Folder holding the previous versions of overwritten files
+    pub backup_dir: Option<String>,
+    /// Install log, used to uninstall the bundle later
+    pub log_path: Option<String>,
+    /// Conflicts that still need a decision
+    pub unresolved: Vec<InstallConflict>,
+    pub error: Option<String>,
+}
+
+/// A bundle installed into an OMSI root, from its install log
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstalledBundle {
+    pub bundle: String,
+    /// Unix seconds
+    pub installed_at: u64,
+    pub log_path: String,
+    /// Number of files the bundle added or overwrote
+    pub files: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UninstallRequest {
+    pub omsi_root: String,
+    /// Install log of the bundle to remove (path or file name in `OMSIMapBundler\installs`)
+    pub install_log: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UninstallResult {
+    pub success: bool,
+    /// Files deleted because the bundle added them
+    pub removed: Vec<String>,
+    /// Files put back from the install backup
+    pub restored: Vec<String>,
+    /// Files kept because another installed bundle uses them
+    pub shared: Vec<String>,
+    /// Files kept because they changed since the install
+    pub modified: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// An OMSI 2 installation found through Steam
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OmsiInstallation {
+    pub root: String,
+    /// Steam library holding the installation
+    pub library: Option<String>,
+    /// Map folders relative to `maps` (e.g. `Grundorf` or `Author\Map`)
+    pub maps: Vec<String>,
+}
+
+/// Manual corrections of the detected dependencies, saved per map.
+/// Paths are relative to the OMSI root; a top-level folder (e.g. `Humans`) covers a whole category.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlanOverrides {
+    /// Files or folders left out of the bundle
+    #[serde(default)]
+    pub excluded: Vec<String>,
+    /// Files or folders always bundled, even when not detected or inside an excluded folder
+    #[serde(default)]
+    pub included: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanState {
+    Included,
+    Excluded,
+    /// Included because of an override
+    Forced,
+}
+
+/// A file or folder of the bundle plan
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlanNode {
+    pub name: String,
+    /// Path relative to the OMSI root
+    pub path: String,
+    pub is_dir: bool,
+    /// Size in bytes (sum of the files for folders)
+    pub size: u64,
+    pub state: PlanState,
+    /// Referenced but not found in the OMSI folder
+    pub missing: bool,
+    /// How the file was found (None for folders)
+    pub source: Option<DependencySource>,
+    pub confidence: Option<Confidence>,
+    pub children: Vec<PlanNode>,
+}
+
+/// Resolved dependencies of a bundle request, before building it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundlePlan {
+    pub omsi_root: String,
+    /// Saved overrides of the bundled maps
+    pub overrides: PlanOverrides,
+    pub nodes: Vec<PlanNode>,
+    /// Files that will be bundled
+    pub files: usize,
+    pub excluded: usize,
+    pub missing: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlanOverridesResult {
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// A map of an OMSI root with the metadata from its global.cfg
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MapInfo {
+    /// Map folder relative to `maps`
+    pub folder: String,
+    pub path: String,
+    /// Friendly name, falling back to the name and then the folder
+    pub name: String,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub tiles: usize,
+    pub entry_points: Vec<String>,
+    /// Picture shown in the OMSI map selection
+    pub preview: Option<String>,
+    /// Newest modification time of the map files (Unix seconds)
+    pub modified: u64,
+    pub error: Option<String>,
+}
+
+/// Paths collected from the map files in phase 1, grouped by extension
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub map_folder: String,
+    pub total: usize,
+    pub groups: Vec<ScanReportGroup>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanReportGroup {
+    pub extension: String,
+    pub paths: Vec<String>,
+}
@@ -1,8 +1,167 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
 use std::fs::File;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use mtzip::level::CompressionLevel;
 use mtzip::ZipArchive;
-use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+use crate::archive::ArchiveOptions;
+
+/// Application identifier, must match `identifier` in tauri.conf.json
+pub const APP_IDENTIFIER: &str = "com.kubiczeek.omsimapbundler";
+
+/// Per-user cache directory of the application (same location Tauri uses for `app_cache_dir`)
+pub fn app_cache_dir() -> PathBuf {
+    let base = if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Library").join("Caches"))
+    } else {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+    };
+    base.unwrap_or_else(std::env::temp_dir).join(APP_IDENTIFIER)
+}
+
+/// Per-user data directory of the application (same location Tauri uses for `app_data_dir`)
+pub fn app_data_dir() -> PathBuf {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("share")))
+    };
+    base.unwrap_or_else(std::env::temp_dir).join(APP_IDENTIFIER)
+}
+
+/// Per-user log directory of the application (same location Tauri uses for `app_log_dir`)
+pub fn app_log_dir() -> PathBuf {
+    let base = if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(|p| PathBuf::from(p).join(APP_IDENTIFIER))
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Library").join("Logs").join(APP_IDENTIFIER))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("share")))
+            .map(|p| p.join(APP_IDENTIFIER))
+    };
+    base.unwrap_or_else(|| std::env::temp_dir().join(APP_IDENTIFIER)).join("logs")
+}
+
+/// Limits of what mtzip can write, which only writes ZIP32 records
+#[derive(Debug, Clone, Copy)]
+struct Zip32Limits {
+    entries: usize,
+    /// Total size of the files
+    size: u64,
+}
+
+/// Archives beyond these limits are written with ZIP64. The size leaves headroom below 4 GiB
+/// for headers and incompressible files growing slightly under deflate.
+const ZIP32_LIMITS: Zip32Limits = Zip32Limits {
+    entries: u16::MAX as usize,
+    size: u32::MAX as u64 - 256 * 1024 * 1024,
+};
+
+/// Which writer a ZIP archive is created with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ZipWriterKind {
+    /// mtzip, compressing on all cores
+    Parallel,
+    /// The zip crate, one entry after the other
+    Sequential,
+}
+
+fn zip_writer_kind(entry_count: usize, total_size: u64, options: &ArchiveOptions, limits: Zip32Limits) -> ZipWriterKind {
+    if entry_count > limits.entries || total_size > limits.size {
+        log_info!(
+            "[ZIP] {} entries, {} MB exceed the ZIP32 limits, writing ZIP64",
+            entry_count,
+            total_size / (1024 * 1024)
+        );
+        return ZipWriterKind::Sequential;
+    }
+    // mtzip writes entries in the order its threads finish and takes timestamps from the files
+    if options.fixed_mtime.is_some() {
+        log_debug!("[ZIP] Reproducible mode, writing entries sequentially");
+        return ZipWriterKind::Sequential;
+    }
+    // mtzip labels every file as deflated, even when it stores it
+    if is_stored(options) {
+        log_debug!("[ZIP] Storing without compression, writing entries sequentially");
+        return ZipWriterKind::Sequential;
+    }
+    ZipWriterKind::Parallel
+}
+
+fn is_stored(options: &ArchiveOptions) -> bool {
+    matches!(options.compression_method.as_str(), "stored" | "store")
+}
+
+/// Deflate level of the ZIP writers
+fn deflate_level(options: &ArchiveOptions) -> u8 {
+    options.compression_level.clamp(1, 9)
+}
+
+/// Make sure the temp and output locations can hold a bundle of `required` bytes.
+/// The archive may be as large as its contents, so a shared volume needs room for both.
+pub fn check_free_space(temp_dir: &Path, output_dir: &Path, required: u64) -> Result<(), String> {
+    let same = match (volume_id(temp_dir), volume_id(output_dir)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    };
+
+    let checks: &[(&Path, u64)] = if same {
+        &[(temp_dir, required.saturating_mul(2))]
+    } else {
+        &[(temp_dir, required), (output_dir, required)]
+    };
+
+    for (dir, needed) in checks {
+        // Skip the check if the free space can't be determined
+        if let Some(available) = available_space(dir) {
+            if available < *needed {
+                return Err(format!(
+                    "Not enough free disk space in {}: {} MB required, {} MB available",
+                    dir.display(),
+                    needed / (1024 * 1024),
+                    available / (1024 * 1024)
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Free space of the volume holding `path` (or its nearest existing ancestor)
+fn available_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    fs4::available_space(existing).ok()
+}
+
+/// Identifier of the volume holding `path`, used to detect a shared temp and output disk
+fn volume_id(path: &Path) -> Option<String> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata(existing).ok().map(|m| m.dev().to_string())
+    }
+    #[cfg(not(unix))]
+    {
+        // Drive letter or UNC share
+        let absolute = fs::canonicalize(existing).ok()?;
+        absolute.components().next().map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+    }
+}
 
 // Helper function to copy a file and create parent directories
 pub fn copy_file_with_folders(src: &Path, dest: &Path) -> std::io::Result<()> {
@@ -13,57 +172,325 @@ pub fn copy_file_with_folders(src: &Path, dest: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
-// Helper function to create ZIP from directory with configurable compression
-pub fn create_zip(
+// Create a ZIP from the files and folders below `src_dir`
+// Archive names are relative to `src_dir`; folders must be listed to get directory entries
+// mtzip marks every entry name as UTF-8 (general purpose flag bit 11), so non-ASCII
+// names are stored correctly unless the filename policy rewrites them to ASCII
+// `progress` is called with (entries done, total entries) while the archive is written
+pub fn create_zip_from_entries(
+    src_dir: &Path,
+    entries: &[PathBuf],
+    dest_file: &Path,
+    options: &ArchiveOptions,
+    progress: &dyn Fn(usize, usize),
+) -> std::io::Result<()> {
+    write_zip(src_dir, entries, dest_file, options, progress, ZIP32_LIMITS)
+}
+
+fn write_zip(
     src_dir: &Path,
+    entries: &[PathBuf],
     dest_file: &Path,
-    _compression_method: &str,
-    _compression_level: u8,
+    options: &ArchiveOptions,
+    progress: &dyn Fn(usize, usize),
+    limits: Zip32Limits,
 ) -> std::io::Result<()> {
+    let filename_policy = options.filename_policy;
+    // Entries done (folders right away), must outlive the zipper like `entries`
+    let done = AtomicUsize::new(entries.iter().filter(|p| !p.is_file()).count());
+    // mtzip stores references to paths, so `entries` must outlive the zipper.write() call
     let mut zipper = ZipArchive::new();
-    
-    // We need to collect paths into a vector to ensure they live long enough for the zipper
-    // mtzip stores references to paths, so the paths must outlive the zipper.write() call
-    let mut entries = Vec::new();
-    for entry in WalkDir::new(src_dir).into_iter().filter_map(|e| e.ok()) {
-        entries.push(entry.path().to_path_buf());
-    }
 
-    println!("[ZIP] Found {} entries to compress in {:?}", entries.len(), src_dir);
+    log_debug!("[ZIP] Found {} entries to compress in {:?}", entries.len(), src_dir);
+
+    let total_size: u64 = entries.iter()
+        .filter_map(|p| fs::metadata(p).ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum();
+    if zip_writer_kind(entries.len(), total_size, options, limits) == ZipWriterKind::Sequential {
+        return create_zip_sequential(src_dir, entries, dest_file, options, progress);
+    }
+    let level = CompressionLevel::new(deflate_level(options)).unwrap_or(CompressionLevel::fast());
 
     // 1. Add all directories first
     let mut dir_count = 0;
-    for path in &entries {
+    for path in entries {
         if path.is_dir() {
             let name = path.strip_prefix(src_dir).unwrap();
             if name.as_os_str().is_empty() {
                 continue;
             }
-            let name_str = name.to_string_lossy().replace('\\', "/");
-            zipper.add_directory(name_str.to_owned()).done();
+            let name_str = filename_policy.archive_name(&name.to_string_lossy().replace('\\', "/"));
+            zipper.add_directory(name_str).done();
             dir_count += 1;
         }
     }
-    println!("[ZIP] Added {} directories", dir_count);
+    log_debug!("[ZIP] Added {} directories", dir_count);
 
-    // 2. Add all files
+    // 2. Add all files; every file counts as done once mtzip has read it to the end
     let mut file_count = 0;
-    for path in &entries {
+    for path in entries {
         if path.is_file() {
             let name = path.strip_prefix(src_dir).unwrap();
-            let name_str = name.to_string_lossy().replace('\\', "/");
-            zipper.add_file_from_fs(path.as_path(), name_str.to_owned()).done();
+            let name_str = filename_policy.archive_name(&name.to_string_lossy().replace('\\', "/"));
+            let reader = CountingReader { path, file: None, finished: false, done: &done };
+            zipper.add_file_from_reader(reader, name_str)
+                .compression_level(level)
+                .metadata_from_fs(path)?
+                .done();
             file_count += 1;
         }
     }
-    println!("[ZIP] Added {} files", file_count);
+    log_debug!("[ZIP] Added {} files", file_count);
 
-    println!("[ZIP] Writing ZIP file to {:?}", dest_file);
+    log_debug!("[ZIP] Writing ZIP file to {:?}", dest_file);
     let mut file = File::create(dest_file)?;
-    zipper.write(&mut file).map_err(|e| {
-        println!("[ZIP ERROR] Failed to write: {}", e);
+    // mtzip compresses on its own threads, report its progress from here until it is done
+    let written = std::thread::scope(|s| {
+        let writer = s.spawn(|| zipper.write(&mut file));
+        while !writer.is_finished() {
+            progress(done.load(Ordering::Relaxed), entries.len());
+            std::thread::sleep(PROGRESS_POLL_INTERVAL);
+        }
+        writer.join().unwrap_or_else(|_| Err(io::Error::other("ZIP writer panicked")))
+    });
+    written.map_err(|e| {
+        log_error!("[ZIP] Failed to write: {}", e);
         std::io::Error::new(std::io::ErrorKind::Other, e)
     })?;
-    println!("[ZIP] Successfully written {} bytes", file.metadata()?.len());
+    progress(entries.len(), entries.len());
+    log_info!("[ZIP] Successfully written {} bytes", file.metadata()?.len());
+    Ok(())
+}
+
+/// How often the progress of the parallel ZIP writer is reported
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// File reader for mtzip that opens the file on first read (mtzip would otherwise keep every
+/// file open until it is compressed) and counts the file as done once it was read to the end
+struct CountingReader<'a> {
+    path: &'a Path,
+    file: Option<File>,
+    finished: bool,
+    done: &'a AtomicUsize,
+}
+
+impl Read for CountingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(File::open(self.path)?),
+        };
+        let read = file.read(buf)?;
+        if read == 0 && !buf.is_empty() && !self.finished {
+            self.finished = true;
+            self.done.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(read)
+    }
+}
+
+// Sequential writer, used for ZIP64 archives mtzip can't represent, for reproducible archives
+// and for stored entries.
+// Entries are written in the given order; ZIP64 records are only added where needed.
+fn create_zip_sequential(
+    src_dir: &Path,
+    entries: &[PathBuf],
+    dest_file: &Path,
+    options: &ArchiveOptions,
+    progress: &dyn Fn(usize, usize),
+) -> io::Result<()> {
+    let filename_policy = options.filename_policy;
+    let mut writer = ZipWriter::new(File::create(dest_file)?);
+    let base_options = match options.fixed_mtime {
+        Some(mtime) => SimpleFileOptions::default().last_modified_time(dos_date_time(mtime)),
+        None => SimpleFileOptions::default(),
+    };
+    let fixed = options.fixed_mtime.is_some();
+    let (method, level) = if is_stored(options) {
+        (CompressionMethod::Stored, None)
+    } else {
+        (CompressionMethod::Deflated, Some(i64::from(deflate_level(options))))
+    };
+
+    for (i, path) in entries.iter().enumerate() {
+        let name = match path.strip_prefix(src_dir) {
+            Ok(name) if !name.as_os_str().is_empty() => name,
+            _ => continue,
+        };
+        let name_str = filename_policy.archive_name(&name.to_string_lossy().replace('\\', "/"));
+
+        if path.is_dir() {
+            let dir_options = if fixed { base_options.unix_permissions(0o755) } else { base_options };
+            writer.add_directory(name_str, dir_options).map_err(io::Error::other)?;
+        } else if path.is_file() {
+            let size = fs::metadata(path)?.len();
+            let file_options = base_options
+                .compression_method(method)
+                .compression_level(level)
+                .large_file(size >= u32::MAX as u64);
+            let file_options = if fixed { file_options.unix_permissions(0o644) } else { file_options };
+            writer.start_file(name_str, file_options).map_err(io::Error::other)?;
+            io::copy(&mut File::open(path)?, &mut writer)?;
+        }
+        progress(i + 1, entries.len());
+    }
+
+    let file = writer.finish().map_err(|e| {
+        log_error!("[ZIP] Failed to write: {}", e);
+        io::Error::other(e)
+    })?;
+    log_info!("[ZIP] Successfully written {} bytes (sequential)", file.metadata()?.len());
     Ok(())
 }
+
+// MS-DOS date and time of a Unix timestamp, taken as UTC (DOS times have no time zone)
+fn dos_date_time(unix_secs: i64) -> zip::DateTime {
+    let days = unix_secs.div_euclid(86_400);
+    let secs = unix_secs.rem_euclid(86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = (yoe + era * 400 + i64::from(month <= 2)) as u16;
+    zip::DateTime::from_date_and_time(year, month, day, (secs / 3600) as u8, (secs / 60 % 60) as u8, (secs % 60) as u8)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use crate::archive_names::FilenamePolicy;
+    use crate::test_utils::{test_dir, write_files};
+
+    fn options(method: &str, level: u8) -> ArchiveOptions {
+        ArchiveOptions {
+            compression_method: method.to_string(),
+            compression_level: level,
+            filename_policy: FilenamePolicy::Utf8,
+            fixed_mtime: None,
+        }
+    }
+
+    // Entries of a folder in walk order, like the bundler passes them
+    fn entries(dir: &Path) -> Vec<PathBuf> {
+        walkdir::WalkDir::new(dir).sort_by_file_name().into_iter().map(|e| e.unwrap().into_path()).collect()
+    }
+
+    // (name, compression method, content) of every entry, in archive order
+    fn read_zip(path: &Path) -> Vec<(String, CompressionMethod, String)> {
+        let mut archive = zip::ZipArchive::new(File::open(path).unwrap()).unwrap();
+        (0..archive.len()).map(|i| {
+            let mut file = archive.by_index(i).unwrap();
+            let mut content = String::new();
+            file.read_to_string(&mut content).unwrap();
+            (file.name().to_string(), file.compression(), content)
+        }).collect()
+    }
+
+    // Text that deflates better at higher levels
+    fn words(count: usize) -> String {
+        let mut seed = 7u32;
+        (0..count).map(|_| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            ["bus", "stop", "road", "tram", "depot", "spline", "texture", "night"][(seed >> 16) as usize % 8]
+        }).collect::<Vec<_>>().join(" ")
+    }
+
+    fn parts(time: zip::DateTime) -> (u16, u8, u8, u8, u8, u8) {
+        (time.year(), time.month(), time.day(), time.hour(), time.minute(), time.second())
+    }
+
+    #[test]
+    fn converts_dos_times_from_1980() {
+        assert_eq!(parts(dos_date_time(315_532_800)), (1980, 1, 1, 0, 0, 0));
+        assert_eq!(parts(dos_date_time(315_532_800 + 86_400 * 59 + 3_723)), (1980, 2, 29, 1, 2, 2));
+        assert_eq!(parts(dos_date_time(1_700_000_000)), (2023, 11, 14, 22, 13, 20));
+        // Earlier times cannot be stored and fall back to the earliest DOS time
+        assert_eq!(parts(dos_date_time(315_532_799)), (1980, 1, 1, 0, 0, 0));
+        assert_eq!(parts(dos_date_time(0)), (1980, 1, 1, 0, 0, 0));
+    }
+
+    #[test]
+    fn picks_the_sequential_writer_beyond_the_zip32_limits() {
+        let limits = Zip32Limits { entries: 3, size: 100 };
+        let deflate = options("deflate", 6);
+        assert_eq!(zip_writer_kind(3, 100, &deflate, limits), ZipWriterKind::Parallel);
+        assert_eq!(zip_writer_kind(4, 100, &deflate, limits), ZipWriterKind::Sequential);
+        assert_eq!(zip_writer_kind(3, 101, &deflate, limits), ZipWriterKind::Sequential);
+        assert_eq!(zip_writer_kind(1, 1, &options("stored", 6), limits), ZipWriterKind::Sequential);
+        let fixed = ArchiveOptions { fixed_mtime: Some(0), ..options("deflate", 6) };
+        assert_eq!(zip_writer_kind(1, 1, &fixed, limits), ZipWriterKind::Sequential);
+    }
+
+    #[test]
+    fn writes_archives_beyond_the_zip32_limits_sequentially() {
+        let dir = test_dir("utils_zip64");
+        let src = dir.join("bundle");
+        write_files(&src, &[("a.txt", "a"), ("maps/b.txt", "b"), ("z.txt", "z")]);
+        let entries = entries(&src);
+        let dest = dir.join("out.zip");
+        let calls = RefCell::new(Vec::new());
+
+        let limits = Zip32Limits { entries: 2, size: u64::MAX };
+        write_zip(&src, &entries, &dest, &options("deflate", 6), &|done, total| calls.borrow_mut().push((done, total)), limits).unwrap();
+
+        // The sequential writer keeps the walk order, the parallel one writes folders first
+        let names: Vec<String> = read_zip(&dest).into_iter().map(|(name, _, content)| format!("{}={}", name, content)).collect();
+        assert_eq!(names, vec!["a.txt=a", "maps/=", "maps/b.txt=b", "z.txt=z"]);
+        assert_eq!(calls.borrow().last(), Some(&(5, 5)));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn applies_the_compression_options_on_both_writers() {
+        let dir = test_dir("utils_zip_options");
+        let src = dir.join("bundle");
+        let text = words(50_000);
+        write_files(&src, &[("Texture/words.txt", &text)]);
+        let entries = entries(&src);
+
+        let size = |options: &ArchiveOptions, limits: Zip32Limits| {
+            let dest = dir.join("out.zip");
+            write_zip(&src, &entries, &dest, options, &|_, _| {}, limits).unwrap();
+            let read = read_zip(&dest);
+            let file = read.iter().find(|(name, _, _)| name == "Texture/words.txt").unwrap();
+            assert_eq!(file.2, text);
+            (file.1, fs::metadata(&dest).unwrap().len())
+        };
+        let sequential = Zip32Limits { entries: 0, size: 0 };
+        for limits in [ZIP32_LIMITS, sequential] {
+            let (method, fast) = size(&options("deflate", 1), limits);
+            assert_eq!(method, CompressionMethod::Deflated);
+            let (_, best) = size(&options("deflate", 9), limits);
+            assert!(best < fast, "{} >= {}", best, fast);
+        }
+        let (method, stored) = size(&options("stored", 9), ZIP32_LIMITS);
+        assert_eq!(method, CompressionMethod::Stored);
+        assert!(stored > text.len() as u64);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reports_missing_free_space() {
+        let dir = test_dir("utils_free_space");
+        let available = available_space(&dir).unwrap();
+
+        let error = check_free_space(&dir, &dir, u64::MAX).unwrap_err();
+        assert!(error.starts_with("Not enough free disk space"), "{}", error);
+        // A shared volume needs room for the copied files and the archive
+        assert!(check_free_space(&dir, &dir, available / 2 + available / 8).is_err());
+        assert!(check_free_space(&dir, &dir, available / 4).is_ok());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
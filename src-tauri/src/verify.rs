@@ -0,0 +1,408 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use sevenz_rust::{Password, SevenZReader};
+use crate::archive::ArchiveFormat;
+use crate::archive_names::transliterate;
+use crate::encoding::CodePages;
+use crate::file_index::normalize_key;
+use crate::parse_cache::ParseCache;
+use crate::omsi_root::list_map_folders;
+use crate::phase1_collection;
+use crate::phase2_extraction::context::ExtractionContext;
+use crate::phase2_processing;
+use crate::types::{Dependency, VerifyRequest, VerifyResult};
+use crate::volumes::{VolumeManifest, volume_path};
+
+/// Contents of one extracted archive
+#[derive(Default)]
+pub struct Extracted {
+    /// Archive names of the extracted files
+    pub files: Vec<String>,
+    /// Entries that failed their checksum or could not be extracted
+    pub corrupt: Vec<String>,
+}
+
+/// Every archive of an extracted bundle
+pub struct ExtractedBundle {
+    pub archives: Vec<(PathBuf, Extracted)>,
+    /// Volumes named by the manifest that are not next to the given archive
+    pub missing_volumes: Vec<String>,
+}
+
+fn failure(error: String) -> VerifyResult {
+    VerifyResult {
+        success: false,
+        files: 0,
+        corrupt_entries: Vec::new(),
+        missing_entries: Vec::new(),
+        unlisted_entries: Vec::new(),
+        missing_volumes: Vec::new(),
+        maps: Vec::new(),
+        unresolved: Vec::new(),
+        error: Some(error),
+    }
+}
+
+/// Number of extraction folders created by this process, to keep them apart
+static EXTRACTION_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// New temporary folder for extracting a bundle; every call gets its own, so the same bundle
+/// can be verified twice at once
+fn extraction_dir(stem: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "omsi_verify_{}_{}_{}",
+        stem,
+        std::process::id(),
+        EXTRACTION_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Check a written bundle: extract it into a temporary folder (verifying every checksum),
+/// compare each archive with its manifest, then resolve the dependencies of every bundled map
+/// with the extracted folder as OMSI root.
+pub fn verify_bundle(request: VerifyRequest) -> VerifyResult {
+    let archive_path = PathBuf::from(&request.archive_path);
+    let file_name = archive_path.file_name().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+    let format = match ArchiveFormat::from_file_name(&file_name) {
+        Some(format) => format,
+        None => return failure(format!("Unsupported archive: {}", file_name)),
+    };
+    if !archive_path.is_file() {
+        return failure(format!("Archive not found: {}", archive_path.display()));
+    }
+
+    let root = extraction_dir(&format.stem(&file_name));
+    if let Err(e) = fs::create_dir_all(&root) {
+        return failure(format!("Failed to create temp folder: {}", e));
+    }
+
+    let requirements = request.requirements.unwrap_or_default();
+    let result = check_archives(&archive_path, format, &root).and_then(|mut result| {
+        resolve_maps(&root, &requirements, &mut result)?;
+        Ok(result)
+    });
+    let _ = fs::remove_dir_all(&root);
+
+    match result {
+        Ok(mut result) => {
+            result.success = result.corrupt_entries.is_empty()
+                && result.missing_entries.is_empty()
+                && result.unlisted_entries.is_empty()
+                && result.missing_volumes.is_empty()
+                && result.unresolved.is_empty();
+            log_info!(
+                "Verified {}: {} files, {} corrupt, {} unresolved",
+                file_name,
+                result.files,
+                result.corrupt_entries.len(),
+                result.unresolved.len()
+            );
+            result
+        }
+        Err(e) => failure(e),
+    }
+}
+
+/// Extract the archive (and the other volumes of a split bundle) into `root`
+fn check_archives(archive_path: &Path, format: ArchiveFormat, root: &Path) -> Result<VerifyResult, String> {
+    let mut result = VerifyResult { error: None, ..failure(String::new()) };
+    let bundle = extract_bundle(archive_path, format, root)?;
+    result.missing_volumes = bundle.missing_volumes;
+
+    for (path, extracted) in &bundle.archives {
+        log_debug!("[VERIFY] {}: {} files", path.display(), extracted.files.len());
+        if let Some(manifest) = find_manifest(root, extracted) {
+            compare_manifest(&manifest, extracted, &mut result);
+        }
+        result.files += extracted.files.len();
+        result.corrupt_entries.extend(extracted.corrupt.iter().cloned());
+    }
+    Ok(result)
+}
+
+/// Extract a bundle into `root`, checking checksums on the way.
+/// For a volume of a split bundle, the other volumes found next to it are extracted as well.
+pub fn extract_bundle(archive_path: &Path, format: ArchiveFormat, root: &Path) -> Result<ExtractedBundle, String> {
+    let first = extract_archive(archive_path, format, root)
+        .map_err(|e| format!("Failed to read {}: {}", archive_path.display(), e))?;
+    let mut bundle = ExtractedBundle {
+        archives: vec![(archive_path.to_path_buf(), first)],
+        missing_volumes: Vec::new(),
+    };
+
+    // A volume carries a manifest naming the bundle and the number of volumes
+    if let Some(manifest) = find_manifest(root, &bundle.archives[0].1) {
+        let output_path = archive_path.with_file_name(format.with_extension(&manifest.bundle));
+        for index in (1..=manifest.volumes).filter(|&i| i != manifest.volume) {
+            let path = volume_path(&output_path, format, index);
+            if !path.is_file() {
+                bundle.missing_volumes.push(path.to_string_lossy().to_string());
+                continue;
+            }
+            let extracted = extract_archive(&path, format, root)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            bundle.archives.push((path, extracted));
+        }
+    }
+    Ok(bundle)
+}
+
+fn extract_archive(archive: &Path, format: ArchiveFormat, dest: &Path) -> io::Result<Extracted> {
+    let mut out = Extracted::default();
+    match format {
+        ArchiveFormat::Zip => extract_zip(archive, dest, &mut out)?,
+        ArchiveFormat::SevenZ => extract_7z(archive, dest, &mut out)?,
+        ArchiveFormat::TarZst => extract_tar_zst(archive, dest, &mut out)?,
+    }
+    Ok(out)
+}
+
+fn extract_zip(archive: &Path, dest: &Path, out: &mut Extracted) -> io::Result<()> {
+    let mut zip = zip::ZipArchive::new(File::open(archive)?).map_err(io::Error::other)?;
+    for i in 0..zip.len() {
+        let mut entry = match zip.by_index(i) {
+            Ok(entry) => entry,
+            Err(e) => {
+                out.corrupt.push(format!("entry {}: {}", i, e));
+                continue;
+            }
+        };
+        let name = entry.name().to_string();
+        let target = match entry.enclosed_name() {
+            Some(rel) => dest.join(rel),
+            None => {
+                out.corrupt.push(format!("{}: unsafe path", name));
+                continue;
+            }
+        };
+        if entry.is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // The reader checks the CRC once the entry has been read to the end
+        if let Err(e) = io::copy(&mut entry, &mut File::create(&target)?) {
+            out.corrupt.push(format!("{}: {}", name, e));
+        }
+        out.files.push(name);
+    }
+    Ok(())
+}
+
+fn extract_7z(archive: &Path, dest: &Path, out: &mut Extracted) -> io::Result<()> {
+    let mut reader = SevenZReader::open(archive, Password::empty()).map_err(io::Error::other)?;
+    let mut current = String::new();
+    let result = reader.for_each_entries(|entry, data| {
+        current = entry.name().to_string();
+        let target = match safe_join(dest, entry.name()) {
+            Some(target) => target,
+            None => {
+                out.corrupt.push(format!("{}: unsafe path", current));
+                return Ok(true);
+            }
+        };
+        if entry.is_directory() {
+            fs::create_dir_all(&target).map_err(sevenz_rust::Error::io)?;
+            return Ok(true);
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(sevenz_rust::Error::io)?;
+        }
+        let mut file = File::create(&target).map_err(sevenz_rust::Error::io)?;
+        // Solid blocks are checked as a whole, a failure aborts the rest of the archive
+        io::copy(data, &mut file).map_err(sevenz_rust::Error::io)?;
+        out.files.push(current.clone());
+        Ok(true)
+    });
+    if let Err(e) = result {
+        out.corrupt.push(format!("{}: {}", current, e));
+    }
+    Ok(())
+}
+
+fn extract_tar_zst(archive: &Path, dest: &Path, out: &mut Extracted) -> io::Result<()> {
+    // The zstd frame checksum is verified while decoding
+    let decoder = zstd::stream::read::Decoder::new(File::open(archive)?)?;
+    let mut tar = tar::Archive::new(decoder);
+    for entry in tar.entries()? {
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                out.corrupt.push(format!("{}: {}", out.files.last().cloned().unwrap_or_default(), e));
+                break;
+            }
+        };
+        let name = entry.path()?.to_string_lossy().to_string();
+        let is_file = entry.header().entry_type().is_file();
+        match entry.unpack_in(dest) {
+            Ok(true) if is_file => out.files.push(name),
+            Ok(true) => {}
+            Ok(false) => out.corrupt.push(format!("{}: unsafe path", name)),
+            Err(e) => out.corrupt.push(format!("{}: {}", name, e)),
+        }
+    }
+    Ok(())
+}
+
+/// Join an archive name to `dest`, refusing absolute paths and `..`
+fn safe_join(dest: &Path, name: &str) -> Option<PathBuf> {
+    let rel = PathBuf::from(name.replace('\\', "/"));
+    rel.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        .then(|| dest.join(rel))
+}
+
+/// True for the volume manifest stored at the root of every volume
+fn is_volume_manifest(name: &str) -> bool {
+    !name.contains('/') && name.ends_with(".manifest.json")
+}
+
+/// True for bundle metadata at the archive root (volume, maps and sources manifests), which is not installed
+pub fn is_manifest(name: &str) -> bool {
+    is_volume_manifest(name)
+        || (!name.contains('/') && (name.ends_with(".maps.json") || name.ends_with(".sources.json")))
+}
+
+/// Volume manifest stored at the root of an archive
+fn find_manifest(root: &Path, extracted: &Extracted) -> Option<VolumeManifest> {
+    let name = extracted.files.iter().find(|f| is_volume_manifest(f))?;
+    let bytes = fs::read(root.join(name)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Manifest names are the original file names, the archive may hold transliterated ones
+fn compare_manifest(manifest: &VolumeManifest, extracted: &Extracted, result: &mut VerifyResult) {
+    let label = |file: &str| if manifest.volumes > 1 {
+        format!("part {}: {}", manifest.volume, file)
+    } else {
+        file.to_string()
+    };
+    let actual: HashSet<String> = extracted.files.iter()
+        .filter(|f| !is_volume_manifest(f))
+        .map(|f| normalize_key(f))
+        .collect();
+    let mut expected = HashSet::new();
+    for file in &manifest.files {
+        let keys = [normalize_key(file), normalize_key(&transliterate(file))];
+        if !keys.iter().any(|k| actual.contains(k)) {
+            result.missing_entries.push(label(file));
+        }
+        expected.extend(keys);
+    }
+    for file in extracted.files.iter().filter(|f| !is_volume_manifest(f)) {
+        if !expected.contains(&normalize_key(file)) {
+            result.unlisted_entries.push(label(file));
+        }
+    }
+}
+
+/// Re-run phase 1 and 2 for every map in the extracted bundle and collect references
+/// that are neither in the bundle nor covered by a requirement
+fn resolve_maps(root: &Path, requirements: &[String], result: &mut VerifyResult) -> Result<(), String> {
+    let requirements: Vec<String> = requirements.iter().map(|r| normalize_key(r)).collect();
+    let declared = |key: &str| {
+        requirements.iter().any(|r| {
+            key.strip_prefix(r.as_str())
+                .map(|rest| rest.is_empty() || rest.starts_with('\\'))
+                .unwrap_or(false)
+        })
+    };
+
+    let maps_dir = root.join("maps");
+    let maps = list_map_folders(root);
+    if maps.is_empty() {
+        log_warn!("[VERIFY] No map folder with a global.cfg in the bundle");
+    }
+
+    // The bundle is the whole OMSI root; a fresh in-memory parse cache keeps results independent
+    let ctx = ExtractionContext::new(root, ParseCache::default(), CodePages::default());
+    let mut unresolved = BTreeSet::new();
+    for map in &maps {
+        let map_name = map.strip_prefix(&maps_dir).unwrap_or(map).to_string_lossy().replace('/', "\\");
+        let initial = phase1_collection::collect_all_dependencies(map, &ctx.cache, None)
+            .map_err(|e| format!("Map scan of {} failed: {}", map_name, e))?;
+        for dep in phase2_processing::process_dependencies(initial.into_dependencies(), &ctx) {
+            let (reference, resolved) = match &dep {
+                Dependency::Folder(folder) => (format!("{}\\", folder), ctx.index.is_dir(folder)),
+                other => (other.path().to_string(), ctx.index.is_file(other.path())),
+            };
+            if !resolved && !declared(&normalize_key(dep.path())) {
+                unresolved.insert(reference);
+            }
+        }
+        result.maps.push(map_name);
+    }
+    result.unresolved = unresolved.into_iter().collect();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use crate::archive::ArchiveOptions;
+    use crate::archive_names::FilenamePolicy;
+    use crate::bundling::write_bundle;
+    use crate::test_utils::test_dir;
+
+    #[test]
+    fn extracts_every_call_into_its_own_folder() {
+        assert_ne!(extraction_dir("Roads"), extraction_dir("Roads"));
+    }
+
+    #[test]
+    fn reports_corrupt_and_missing_entries() {
+        let dir = test_dir("verify_corrupt");
+        let src = dir.join("bundle");
+        fs::create_dir_all(src.join("Texture")).unwrap();
+        fs::create_dir_all(src.join("Sceneryobjects/Houses")).unwrap();
+        fs::write(src.join("Texture/road.dds"), b"road texture data that gets corrupted").unwrap();
+        fs::write(src.join("Texture/grass.dds"), b"grass texture data").unwrap();
+        fs::write(src.join("Sceneryobjects/Houses/house.sco"), b"[model]\n").unwrap();
+
+        let options = ArchiveOptions {
+            compression_method: "stored".to_string(),
+            compression_level: 1,
+            filename_policy: FilenamePolicy::Utf8,
+            fixed_mtime: None,
+        };
+        let written = dir.join("Written.zip");
+        write_bundle(&src, &written, ArchiveFormat::Zip, None, &options, &None).unwrap();
+        let intact = verify_bundle(VerifyRequest { archive_path: written.to_string_lossy().to_string(), requirements: None });
+        assert!(intact.success, "{:?}", intact);
+        assert_eq!(intact.files, 4);
+
+        // Copy the bundle without grass.dds, under the name its manifest expects
+        let bundle = dir.join("out/Written.zip");
+        fs::create_dir_all(bundle.parent().unwrap()).unwrap();
+        let mut source = zip::ZipArchive::new(File::open(&written).unwrap()).unwrap();
+        let mut copy = zip::ZipWriter::new(File::create(&bundle).unwrap());
+        for i in 0..source.len() {
+            let entry = source.by_index_raw(i).unwrap();
+            if entry.name() != "Texture/grass.dds" {
+                copy.raw_copy_file(entry).unwrap();
+            }
+        }
+        copy.finish().unwrap().flush().unwrap();
+
+        // Flip a byte of the stored road.dds data, its CRC no longer matches
+        let mut bytes = fs::read(&bundle).unwrap();
+        let data = bytes.windows(12).position(|w| w == b"road texture").unwrap();
+        bytes[data] ^= 0xFF;
+        fs::write(&bundle, bytes).unwrap();
+
+        let result = verify_bundle(VerifyRequest { archive_path: bundle.to_string_lossy().to_string(), requirements: None });
+        assert!(!result.success);
+        assert_eq!(result.corrupt_entries.len(), 1, "{:?}", result.corrupt_entries);
+        assert!(result.corrupt_entries[0].starts_with("Texture/road.dds"));
+        assert_eq!(result.missing_entries, vec!["Texture\\grass.dds".to_string()]);
+        assert!(result.unlisted_entries.is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
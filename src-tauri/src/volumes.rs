@@ -0,0 +1,268 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::archive::ArchiveFormat;
+
+/// One self-contained archive of a split bundle
+#[derive(Debug, Default)]
+pub struct Volume {
+    /// Files (absolute paths below the bundle folder)
+    pub files: Vec<PathBuf>,
+    /// Estimated size in the archive (see `entry_size`)
+    pub size: u64,
+}
+
+/// Manifest written at the root of every bundle: one per volume of a split bundle,
+/// a single one listing all files otherwise
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolumeManifest {
+    pub bundle: String,
+    pub volume: usize,
+    pub volumes: usize,
+    /// Top-level folders contained in this volume
+    pub folders: Vec<String>,
+    pub files: Vec<String>,
+}
+
+/// Archive bytes per entry besides its data and name: ZIP local and central headers with
+/// ZIP64 extras and a data descriptor, or a tar header (its zero padding compresses away)
+const ENTRY_OVERHEAD: u64 = 512;
+
+/// Archive bytes per volume besides its entries: the end of central directory records
+/// and the fixed part of the manifest
+const VOLUME_OVERHEAD: u64 = 4096;
+
+/// A group of files that should stay in the same volume if possible
+struct Unit {
+    files: Vec<PathBuf>,
+    size: u64,
+}
+
+/// Split the contents of `src_dir` into volumes of at most `max_size` bytes.
+///
+/// Sizes are estimated for data that does not compress, including headers and the manifest.
+/// Whole top-level folders (maps, Sceneryobjects, Vehicles, ...) are kept together when they fit;
+/// bigger folders are split along their subfolders. Fails if a single file exceeds the limit.
+pub fn plan_volumes(src_dir: &Path, max_size: u64) -> Result<Vec<Volume>, String> {
+    let max_size = max_size.saturating_sub(VOLUME_OVERHEAD);
+    let mut units = Vec::new();
+    collect_units(src_dir, src_dir, max_size, &mut units)?;
+
+    // First-fit decreasing
+    units.sort_by_key(|u| std::cmp::Reverse(u.size));
+    let mut volumes: Vec<Volume> = Vec::new();
+    for unit in units {
+        match volumes.iter_mut().find(|v| v.size + unit.size <= max_size) {
+            Some(volume) => {
+                volume.size += unit.size;
+                volume.files.extend(unit.files);
+            }
+            None => volumes.push(Volume { files: unit.files, size: unit.size }),
+        }
+    }
+    for volume in &mut volumes {
+        volume.files.sort();
+    }
+    Ok(volumes)
+}
+
+fn collect_units(src_dir: &Path, dir: &Path, max_size: u64, units: &mut Vec<Unit>) -> Result<(), String> {
+    // Loose files also need the entries of their folder
+    let folders = folders_size(src_dir, dir);
+    let mut loose = Unit { files: Vec::new(), size: folders };
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            let unit = folder_unit(src_dir, &path);
+            if unit.size <= max_size {
+                units.push(unit);
+            } else {
+                collect_units(src_dir, &path, max_size, units)?;
+            }
+        } else if path.is_file() {
+            let data = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            let size = entry_size(src_dir, &path, data);
+            if folders + size > max_size {
+                return Err(format!(
+                    "{} ({} MB) is larger than the maximum volume size",
+                    path.display(),
+                    data / (1024 * 1024)
+                ));
+            }
+            // Loose files of one folder form their own unit, split when it gets too big
+            if !loose.files.is_empty() && loose.size + size > max_size {
+                units.push(std::mem::replace(&mut loose, Unit { files: Vec::new(), size: folders }));
+            }
+            loose.size += size;
+            loose.files.push(path);
+        }
+    }
+
+    if !loose.files.is_empty() {
+        units.push(loose);
+    }
+    Ok(())
+}
+
+// All files of a folder, with the size of their entries and the folder entries down to them
+fn folder_unit(src_dir: &Path, dir: &Path) -> Unit {
+    let mut files = Vec::new();
+    let mut size = dir.parent().map(|parent| folders_size(src_dir, parent)).unwrap_or(0);
+    for entry in walkdir::WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+        if entry.file_type().is_file() {
+            let data = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            size += entry_size(src_dir, entry.path(), data);
+            files.push(entry.into_path());
+        } else if entry.file_type().is_dir() {
+            size += entry_size(src_dir, entry.path(), 0);
+        }
+    }
+    Unit { files, size }
+}
+
+// Size of the entries of `dir` and its parents below `src_dir`
+fn folders_size(src_dir: &Path, dir: &Path) -> u64 {
+    dir.ancestors()
+        .take_while(|a| *a != src_dir && a.starts_with(src_dir))
+        .map(|a| entry_size(src_dir, a, 0))
+        .sum()
+}
+
+// Space an entry takes up in a volume: its data with a margin for data that grows when compressed,
+// its headers, and its name (in the local and central headers and in the manifest)
+fn entry_size(src_dir: &Path, path: &Path, data: u64) -> u64 {
+    let name = path.strip_prefix(src_dir).map(|p| p.as_os_str().len() as u64).unwrap_or(0);
+    data + data / 1024 + ENTRY_OVERHEAD + 3 * name
+}
+
+/// Path of volume `index` (1-based): `Bundle.zip` becomes `Bundle.part1.zip`, `Bundle.part2.zip`, ...
+pub fn volume_path(output_path: &Path, format: ArchiveFormat, index: usize) -> PathBuf {
+    let file_name = output_path.file_name().and_then(|s| s.to_str()).unwrap_or("bundle");
+    let stem = format.stem(file_name);
+    output_path.with_file_name(format!("{}.part{}.{}", stem, index, format.extension()))
+}
+
+/// Name of the manifest file stored at the root of volume `index` of `count`
+pub fn manifest_name(bundle: &str, index: usize, count: usize) -> String {
+    if count > 1 {
+        format!("{}.part{}.manifest.json", bundle, index)
+    } else {
+        format!("{}.manifest.json", bundle)
+    }
+}
+
+/// Archive entries for a volume: its files plus every parent folder below `src_dir`
+pub fn volume_entries(src_dir: &Path, volume: &Volume) -> Vec<PathBuf> {
+    let mut folders = BTreeSet::new();
+    for file in &volume.files {
+        for ancestor in file.ancestors().skip(1) {
+            if ancestor == src_dir || !ancestor.starts_with(src_dir) {
+                break;
+            }
+            folders.insert(ancestor.to_path_buf());
+        }
+    }
+    folders.into_iter().chain(volume.files.iter().cloned()).collect()
+}
+
+/// Describe the contents of a volume
+pub fn build_manifest(src_dir: &Path, bundle: &str, index: usize, count: usize, volume: &Volume) -> VolumeManifest {
+    let files: Vec<String> = volume.files.iter()
+        .filter_map(|f| f.strip_prefix(src_dir).ok())
+        .map(|f| f.to_string_lossy().replace('/', "\\"))
+        .collect();
+    let folders: BTreeSet<String> = files.iter()
+        .filter_map(|f| f.split_once('\\'))
+        .map(|(folder, _)| folder.to_string())
+        .collect();
+    VolumeManifest {
+        bundle: bundle.to_string(),
+        volume: index,
+        volumes: count,
+        folders: folders.into_iter().collect(),
+        files,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_dir;
+
+    fn bundle_dir(name: &str, files: &[(&str, usize)]) -> PathBuf {
+        let dir = test_dir(&format!("volumes_{}", name));
+        for (path, size) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, vec![0u8; *size]).unwrap();
+        }
+        dir
+    }
+
+    fn top_level(dir: &Path, volume: &Volume) -> BTreeSet<String> {
+        volume.files.iter()
+            .filter_map(|f| f.strip_prefix(dir).ok()?.components().next())
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_top_level_folders_together() {
+        let dir = bundle_dir("top_level", &[
+            ("maps/Grundorf/global.cfg", 30_000),
+            ("maps/Grundorf/tile_0_0.map", 30_000),
+            ("Sceneryobjects/Houses/house.sco", 30_000),
+            ("Sceneryobjects/Houses/model/house.o3d", 30_000),
+            ("Vehicles/Bus/bus.bus", 50_000),
+        ]);
+        let volumes = plan_volumes(&dir, 100_000).unwrap();
+
+        assert_eq!(volumes.len(), 3);
+        let mut seen = BTreeSet::new();
+        for volume in &volumes {
+            let folders = top_level(&dir, volume);
+            assert_eq!(folders.len(), 1, "{:?}", folders);
+            assert!(seen.insert(folders.into_iter().next().unwrap()));
+        }
+        assert_eq!(volumes.iter().map(|v| v.files.len()).sum::<usize>(), 5);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn splits_big_folders_within_the_limit() {
+        let dir = bundle_dir("limit", &[
+            ("Sceneryobjects/A/a1.o3d", 40_000),
+            ("Sceneryobjects/A/a2.o3d", 40_000),
+            ("Sceneryobjects/B/b1.o3d", 40_000),
+            ("Sceneryobjects/loose.sco", 40_000),
+        ]);
+        let max_size = 100_000;
+        let volumes = plan_volumes(&dir, max_size).unwrap();
+
+        assert_eq!(volumes.len(), 2);
+        for volume in &volumes {
+            let data: u64 = volume.files.iter().map(|f| fs::metadata(f).unwrap().len()).sum();
+            let entries = volume_entries(&dir, volume).len() as u64;
+            // Headers and the manifest are part of the limit
+            assert!(volume.size > data + entries * ENTRY_OVERHEAD);
+            assert!(volume.size + VOLUME_OVERHEAD <= max_size);
+        }
+        let with_a2 = volumes.iter().find(|v| v.files.iter().any(|f| f.ends_with("a2.o3d"))).unwrap();
+        assert_eq!(with_a2.files.len(), 2);
+        let _ = fs::remove_dir_all(&dir);
+
+        // Two files that only fit together without their headers
+        let dir = bundle_dir("exact", &[("Texture/a.dds", 50_000), ("Texture/b.dds", 50_000)]);
+        assert_eq!(plan_volumes(&dir, 100_000).unwrap().len(), 2);
+        assert!(plan_volumes(&dir, 40_000).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
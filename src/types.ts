@@ -18,18 +18,212 @@ export interface DependencyResult {
   error?: string;
 }
 
+export interface AddonFolder {
+  path: string;
+  mount?: string;
+}
+
 export interface BundleRequest {
   map_folder: string;
+  map_folders?: string[];
+  assets?: string[];
+  omsi_root?: string;
+  asset_roots?: string[];
   addon_folder?: string;
+  addon_folders?: AddonFolder[];
   output_folder?: string;
   zip_name?: string;
   readme_path?: string;
   compression_method?: string;
   compression_level?: number;
+  log_level?: "error" | "warn" | "info" | "debug";
+  include_scan_report?: boolean;
+  scan_report_path?: string;
+  code_page?: string;
+  folder_code_pages?: Record<string, string>;
+  filename_policy?: "utf8" | "transliterate" | "reject";
+  max_volume_size_mb?: number;
+  archive_format?: "zip" | "7z" | "tar.zst";
+  reproducible?: boolean;
+  source_date_epoch?: number;
+  disabled_sources?: DependencySource[];
+}
+
+export type DependencySource = "section" | "path_scan" | "binary_scan" | "prefix_match";
+
+export type Confidence = "exact" | "heuristic";
+
+export interface DependencyTag {
+  path: string;
+  source: DependencySource;
+  confidence: Confidence;
+  bundled: boolean;
+}
+
+export type DependencyKind = "file" | "folder" | "optional" | "missing" | "excluded";
+
+export type DependencyCategory =
+  | "sceneryobjects"
+  | "splines"
+  | "textures"
+  | "vehicles"
+  | "humans"
+  | "trains"
+  | "sounds"
+  | "scripts"
+  | "money_systems"
+  | "ticket_packs"
+  | "maps"
+  | "other";
+
+export interface DependencyEntry {
+  kind: DependencyKind;
+  path: string;
+  category: DependencyCategory;
+  source: DependencySource;
+}
+
+export interface ScanReportGroup {
+  extension: string;
+  paths: string[];
+}
+
+export interface ScanReport {
+  map_folder: string;
+  total: number;
+  groups: ScanReportGroup[];
 }
 
 export interface BundleResult {
   success: boolean;
   output_path?: string;
+  output_paths: string[];
+  error?: string;
+  scan_report?: ScanReport;
+  non_ascii_names: string[];
+  heuristic_dependencies: DependencyTag[];
+  dependencies: DependencyEntry[];
+}
+
+export interface VerifyRequest {
+  archive_path: string;
+  requirements?: string[];
+}
+
+export interface VerifyResult {
+  success: boolean;
+  files: number;
+  corrupt_entries: string[];
+  missing_entries: string[];
+  unlisted_entries: string[];
+  missing_volumes: string[];
+  maps: string[];
+  unresolved: string[];
+  error?: string;
+}
+
+export interface InstallRequest {
+  archive_path: string;
+  omsi_root: string;
+  resolutions?: Record<string, "keep" | "overwrite">;
+  default_resolution?: "keep" | "overwrite";
+}
+
+export interface InstallConflict {
+  path: string;
+  existing_size: number;
+  bundle_size: number;
+  existing_hash: string;
+  bundle_hash: string;
+}
+
+export interface InstallPlan {
+  new_files: string[];
+  identical: string[];
+  conflicts: InstallConflict[];
   error?: string;
 }
+
+export interface InstallResult {
+  success: boolean;
+  installed: string[];
+  kept: string[];
+  overwritten: string[];
+  backup_dir?: string;
+  log_path?: string;
+  unresolved: InstallConflict[];
+  error?: string;
+}
+
+export interface InstalledBundle {
+  bundle: string;
+  installed_at: number;
+  log_path: string;
+  files: number;
+}
+
+export interface UninstallRequest {
+  omsi_root: string;
+  install_log: string;
+}
+
+export interface UninstallResult {
+  success: boolean;
+  removed: string[];
+  restored: string[];
+  shared: string[];
+  modified: string[];
+  error?: string;
+}
+
+export interface PlanOverrides {
+  excluded: string[];
+  included: string[];
+}
+
+export interface PlanNode {
+  name: string;
+  path: string;
+  is_dir: boolean;
+  size: number;
+  state: "included" | "excluded" | "forced";
+  missing: boolean;
+  source?: DependencySource;
+  confidence?: Confidence;
+  children: PlanNode[];
+}
+
+export interface BundlePlan {
+  omsi_root: string;
+  overrides: PlanOverrides;
+  nodes: PlanNode[];
+  files: number;
+  excluded: number;
+  missing: number;
+  error?: string;
+}
+
+export interface PlanOverridesResult {
+  success: boolean;
+  error?: string;
+}
+
+export interface MapInfo {
+  folder: string;
+  path: string;
+  name: string;
+  description?: string;
+  version?: string;
+  author?: string;
+  tiles: number;
+  entry_points: string[];
+  preview?: string;
+  modified: number;
+  error?: string;
+}
+
+export interface OmsiInstallation {
+  root: string;
+  library?: string;
+  maps: string[];
+}